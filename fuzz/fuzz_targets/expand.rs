@@ -0,0 +1,24 @@
+#![no_main]
+
+use fesh::expander::Expander;
+use fesh::input_parser::InputParser;
+use libfuzzer_sys::fuzz_target;
+
+// the input is split into a variable value and a command line, e.g. "a b\0echo $FESH_FUZZ"
+fuzz_target!(|input: &str| {
+    let (value, line) = input.split_once('\0').unwrap_or(("", input));
+
+    let mut expander = Expander::new();
+    expander.variables.set_scalar("FESH_FUZZ", value.to_string());
+    expander.variables.positional_params = value.split(' ').map(String::from).collect();
+
+    if let Ok(mut command_list) = InputParser::new().parse_raw(line.to_string()) {
+        for command in &command_list.commands {
+            for assignment in &command.assignments {
+                let _ = expander.apply_assignment(assignment);
+            }
+        }
+        expander.expand_command_list(&mut command_list);
+    }
+    let _ = expander.expand_text(line);
+});
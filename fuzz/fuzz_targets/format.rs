@@ -0,0 +1,15 @@
+#![no_main]
+
+use fesh::formatter::Formatter;
+use fesh::input_parser::InputParser;
+use libfuzzer_sys::fuzz_target;
+
+// formatting must not change the parse tree and must be idempotent
+fuzz_target!(|input: &str| {
+    let parser = InputParser::new();
+    let formatter = Formatter::new();
+    if let (Ok(expected), Ok(formatted)) = (parser.parse_script(input), formatter.format(input)) {
+        assert_eq!(parser.parse_script(&formatted).unwrap(), expected);
+        assert_eq!(formatter.format(&formatted).unwrap(), formatted);
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use fesh::input_parser::InputParser;
+use libfuzzer_sys::fuzz_target;
+
+// the tokenizer and the parser must return an error instead of panicking
+fuzz_target!(|input: &str| {
+    let parser = InputParser::new();
+    let _ = parser.tokenizer.tokenize_spanned(input);
+    let _ = parser.parse(input.to_string());
+    let _ = parser.parse_script(input);
+});
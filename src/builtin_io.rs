@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+
+use nix::unistd::{self, dup2_stderr, dup2_stdin, dup2_stdout};
+
+// where a builtin reads from, the shell's stdin or a pipe or file from a redirect
+pub enum Input {
+    Stdin,
+    File(File),
+}
+
+impl Read for Input {
+    // stdin is read without a buffer, what a builtin doesn't read is left for later commands
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::Stdin => unistd::read(io::stdin().as_fd(), buf).map_err(io::Error::from),
+            Input::File(file) => file.read(buf),
+        }
+    }
+}
+
+impl AsFd for Input {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        match self {
+            // the fd of stdin stays open as long as the shell runs
+            Input::Stdin => unsafe { BorrowedFd::borrow_raw(0) },
+            Input::File(file) => file.as_fd(),
+        }
+    }
+}
+
+// where a builtin writes to, the shell's stdout or stderr or a pipe or file from a redirect
+pub enum Output {
+    Stdout,
+    Stderr,
+    File(File),
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout => io::stdout().write(buf),
+            Output::Stderr => io::stderr().write(buf),
+            Output::File(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout => io::stdout().flush(),
+            Output::Stderr => io::stderr().flush(),
+            Output::File(file) => file.flush(),
+        }
+    }
+}
+
+// the stdin, stdout and stderr of a builtin
+pub struct BuiltinIo {
+    pub stdin: Input,
+    pub stdout: Output,
+    pub stderr: Output,
+}
+
+impl BuiltinIo {
+    // fds that are None use the stream of the shell
+    pub fn new(fds: [Option<OwnedFd>; 3]) -> Self {
+        let [stdin, stdout, stderr] = fds;
+        BuiltinIo {
+            stdin: stdin.map(|fd| Input::File(File::from(fd))).unwrap_or(Input::Stdin),
+            stdout: stdout.map(|fd| Output::File(File::from(fd))).unwrap_or(Output::Stdout),
+            stderr: stderr.map(|fd| Output::File(File::from(fd))).unwrap_or(Output::Stderr),
+        }
+    }
+
+    // makes the redirected streams the shell's own stdin, stdout and stderr, so commands
+    // started later inherit them
+    pub fn install(&mut self) -> io::Result<()> {
+        self.flush();
+        let _ = io::stdout().flush();
+        if let Input::File(file) = &self.stdin {
+            dup2_stdin(file)?;
+        }
+        if let Output::File(file) = &self.stdout {
+            dup2_stdout(file)?;
+        }
+        if let Output::File(file) = &self.stderr {
+            dup2_stderr(file)?;
+        }
+        *self = BuiltinIo::new([None, None, None]);
+        Ok(())
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.stdout.flush();
+        let _ = self.stderr.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_to_pipe() {
+        let (mut reader, writer) = io::pipe().unwrap();
+        let mut builtin_io = BuiltinIo::new([None, Some(writer.into()), None]);
+        writeln!(builtin_io.stdout, "a -> b").unwrap();
+        drop(builtin_io);
+
+        let mut output = String::new();
+        reader.read_to_string(&mut output).unwrap();
+        assert_eq!(output, "a -> b\n");
+    }
+}
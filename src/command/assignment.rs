@@ -0,0 +1,163 @@
+use serde::Serialize;
+
+use crate::command::word::{Word, WordPart};
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum AssignmentValue {
+    Scalar(Word),          // name=value
+    Array(Vec<Word>),      // name=(a b c)
+}
+
+// a variable assignment like `name=value`, `name[sub]=value`, `name+=value` or `name=(a b)`
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Assignment {
+    pub name: String,
+    pub subscript: Option<Word>,
+    pub append: bool,
+    pub value: AssignmentValue,
+}
+
+// a word split into unquoted characters and other parts, so the `=` can be located
+enum Item<'a> {
+    Char(char),
+    Part(&'a WordPart),
+}
+
+impl Assignment {
+    pub fn parse(word: &Word) -> Option<Self> {
+        let items = Self::items(word);
+        let mut i = 0;
+
+        let mut name = String::new();
+        while let Some(Item::Char(c)) = items.get(i) {
+            if !(c.is_ascii_alphanumeric() || *c == '_') {
+                break;
+            }
+            name.push(*c);
+            i += 1;
+        }
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let (subscript, next) = Self::parse_subscript(&items, i)?;
+        i = next;
+
+        let append = matches!(items.get(i), Some(Item::Char('+')));
+        if append {
+            i += 1;
+        }
+        if !matches!(items.get(i), Some(Item::Char('='))) {
+            return None;
+        }
+        i += 1;
+
+        let value = match &items[i..] {
+            [Item::Part(WordPart::Array(elements))] if subscript.is_none() => AssignmentValue::Array(elements.clone()),
+            rest => AssignmentValue::Scalar(Self::word_from_items(rest)),
+        };
+
+        Some(Assignment {
+            name,
+            subscript,
+            append,
+            value,
+        })
+    }
+
+    // parses an array literal element like `[key]=value`, returns the key and the value
+    pub fn parse_element(word: &Word) -> Option<(Word, Word)> {
+        let items = Self::items(word);
+        let (subscript, i) = Self::parse_subscript(&items, 0)?;
+        match (subscript, items.get(i)) {
+            (Some(key), Some(Item::Char('='))) => Some((key, Self::word_from_items(&items[i + 1..]))),
+            _ => None,
+        }
+    }
+
+    // parses an optional `[...]` starting at `start`, returns it and the index after it
+    fn parse_subscript(items: &[Item], start: usize) -> Option<(Option<Word>, usize)> {
+        if !matches!(items.get(start), Some(Item::Char('['))) {
+            return Some((None, start));
+        }
+        let end = start + items[start..].iter().position(|item| matches!(item, Item::Char(']')))?;
+        Some((Some(Self::word_from_items(&items[start + 1..end])), end + 1))
+    }
+
+    fn items(word: &Word) -> Vec<Item<'_>> {
+        let mut items = Vec::new();
+        for part in &word.parts {
+            match part {
+                WordPart::Literal(s) => items.extend(s.chars().map(Item::Char)),
+                part => items.push(Item::Part(part)),
+            }
+        }
+        items
+    }
+
+    fn word_from_items(items: &[Item]) -> Word {
+        let mut parts: Vec<WordPart> = Vec::new();
+        for item in items {
+            match item {
+                Item::Char(c) => {
+                    if let Some(WordPart::Literal(s)) = parts.last_mut() {
+                        s.push(*c);
+                    } else {
+                        parts.push(WordPart::Literal(c.to_string()));
+                    }
+                }
+                Item::Part(part) => parts.push((*part).clone()),
+            }
+        }
+        Word::new(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::word::Parameter;
+
+    #[test]
+    fn test_parse_scalar() {
+        let assignment = Assignment::parse(&Word::literal("FOO=bar")).unwrap();
+        assert_eq!(assignment.name, "FOO");
+        assert_eq!(assignment.subscript, None);
+        assert!(!assignment.append);
+        assert_eq!(assignment.value, AssignmentValue::Scalar(Word::literal("bar")));
+    }
+
+    #[test]
+    fn test_parse_subscript_and_append() {
+        let word = Word::new(vec![
+            WordPart::Literal(String::from("arr[")),
+            WordPart::Parameter(Parameter::new("i")),
+            WordPart::Literal(String::from("]+=x")),
+        ]);
+        let assignment = Assignment::parse(&word).unwrap();
+        assert_eq!(assignment.name, "arr");
+        assert_eq!(
+            assignment.subscript,
+            Some(Word::new(vec![WordPart::Parameter(Parameter::new("i"))]))
+        );
+        assert!(assignment.append);
+        assert_eq!(assignment.value, AssignmentValue::Scalar(Word::literal("x")));
+    }
+
+    #[test]
+    fn test_parse_not_an_assignment() {
+        assert_eq!(Assignment::parse(&Word::literal("ls")), None);
+        assert_eq!(Assignment::parse(&Word::literal("=x")), None);
+        assert_eq!(Assignment::parse(&Word::literal("1a=x")), None);
+        assert_eq!(Assignment::parse(&Word::new(vec![WordPart::SingleQuoted(String::from("a=b"))])), None);
+    }
+
+    #[test]
+    fn test_parse_element() {
+        let (key, value) = Assignment::parse_element(&Word::literal("[k]=v")).unwrap();
+        assert_eq!(key, Word::literal("k"));
+        assert_eq!(value, Word::literal("v"));
+        assert_eq!(Assignment::parse_element(&Word::literal("v")), None);
+    }
+}
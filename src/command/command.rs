@@ -1,23 +1,113 @@
+use serde::Serialize;
+
+use crate::command::assignment::Assignment;
 use crate::command::command_type::CommandType;
+use crate::command::redirect::Redirect;
+use crate::command::word::Word;
+
+// commands run by fesh itself
+pub const BUILTINS: [&str; 20] = [
+    "exit", "+debug", "cd", "aliases", "set", "declare", "export", "exec", "hash", "type", "which", "command", "ulimit",
+    "umask", "trap", "source", ".", "eval", "read", "unset",
+];
 
-#[derive(Clone, PartialEq, Debug)]
+// words the parser handles before a pipeline
+pub const KEYWORDS: [&str; 2] = ["time", "!"];
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
 pub struct Command {
+    #[serde(skip)]
     pub command_type: CommandType,
+    #[serde(skip)]
     pub command: String,
+    #[serde(skip)]
     pub args: Vec<String>,
+    pub words: Vec<Word>,
+    pub assignments: Vec<Assignment>,
+    pub redirects: Vec<Redirect>,
 }
 
 impl Command {
     pub fn new(command: String, args: Vec<String>) -> Self {
-        let command_type = match command.as_str() {
-            "exit" | "+debug" | "cd" | "aliases" | "set" => CommandType::Builtin,
-            _ => CommandType::External,
-        };
+        let words = std::iter::once(&command)
+            .chain(args.iter())
+            .map(|s| Word::literal(s))
+            .collect();
+
+        Self {
+            command_type: Self::command_type(&command),
+            command,
+            args,
+            words,
+            assignments: Vec::new(),
+            redirects: Vec::new(),
+        }
+    }
+
+    // creates a command from unexpanded words, command and args hold the words without quotes,
+    // leading `name=value` words become assignments
+    pub fn from_words(words: Vec<Word>) -> Self {
+        let mut assignments = Vec::new();
+        let mut words = words.into_iter().peekable();
+        while let Some(assignment) = words.peek().and_then(Assignment::parse) {
+            assignments.push(assignment);
+            words.next();
+        }
+        let words: Vec<Word> = words.collect();
+
+        let mut texts = words.iter().map(|w| w.text());
+        let command = texts.next().unwrap_or_default();
+        let args = texts.collect();
 
         Self {
-            command_type,
+            command_type: Self::command_type(&command),
             command,
             args,
+            words,
+            assignments,
+            redirects: Vec::new(),
+        }
+    }
+
+    // sets command and args from the fields produced by expansion
+    pub fn set_fields(&mut self, fields: Vec<String>) {
+        let mut fields = fields.into_iter();
+        self.command = fields.next().unwrap_or_default();
+        self.args = fields.collect();
+        self.command_type = Self::command_type(&self.command);
+    }
+
+    pub fn is_builtin(command: &str) -> bool {
+        Self::command_type(command) == CommandType::Builtin
+    }
+
+    // `command name args` runs name like any other command, only aliases are not looked up,
+    // `command -v` and `command -V` stay the builtin
+    pub fn strip_command_prefix(&mut self) {
+        if self.command != "command" {
+            return;
+        }
+        let skipped = match self.args.first().map(String::as_str) {
+            Some("--") if self.args.len() > 1 => 1,
+            Some(arg) if !arg.starts_with('-') => 0,
+            _ => return,
+        };
+        // builtins like declare read their words, the literal prefix words are dropped too
+        for text in std::iter::once("command").chain(self.args[..skipped].iter().map(String::as_str)) {
+            if self.words.first().is_some_and(|w| w.is_plain_literal() && w.text() == text) {
+                self.words.remove(0);
+            }
+        }
+        let mut fields = self.args.split_off(skipped);
+        self.args = fields.split_off(1);
+        self.command = fields.remove(0);
+        self.command_type = Self::command_type(&self.command);
+    }
+
+    fn command_type(command: &str) -> CommandType {
+        if BUILTINS.contains(&command) {
+            return CommandType::Builtin;
         }
+        CommandType::External
     }
 }
@@ -3,12 +3,18 @@ use std::fmt;
 #[derive(Debug, PartialEq)]
 pub enum CommandError {
     Empty,
+    UnterminatedQuote(char),
+    BadSubstitution(String),
+    UnexpectedToken(String),
 }
 
 impl fmt::Display for CommandError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             CommandError::Empty => write!(f, "command input is empty"),
+            CommandError::UnterminatedQuote(q) => write!(f, "unexpected end of input while looking for matching `{}`", q),
+            CommandError::BadSubstitution(s) => write!(f, "{}: bad substitution", s),
+            CommandError::UnexpectedToken(t) => write!(f, "unexpected token `{}`", t),
         }
     }
 }
@@ -1,10 +1,24 @@
 use crate::command::command::Command;
 use crate::command::operator::Operator;
+use crate::command::token::Token;
+use crate::tokenizer::Tokenizer;
+use serde::Serialize;
 use std::collections::HashMap;
-#[derive(Debug)]
+// `time` before a pipeline, `time -p` prints the times in the POSIX format
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Time {
+    Default,
+    Posix,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
 pub struct CommandList {
     pub commands: Vec<Command>,
     pub operators: Vec<Operator>,
+    pub comment: Option<String>,
+    pub negated: bool, // `! cmd`, the exit status is inverted
+    pub time: Option<Time>,
 }
 
 impl CommandList {
@@ -12,22 +26,37 @@ impl CommandList {
         Self {
             commands,
             operators,
+            comment: None,
+            negated: false,
+            time: None,
         }
     }
 
     pub fn replace_aliases(&mut self, aliases: &HashMap<String, String>) {
+        let tokenizer = Tokenizer::new();
         for command in self.commands.iter_mut() {
+            // quoted or escaped command names are never aliases
+            if !command.words.first().is_some_and(|w| w.is_plain_literal()) {
+                continue;
+            }
             if let Some(alias_value) = aliases.get(&command.command) {
-                let parts: Vec<&str> = alias_value.split_whitespace().collect();
-                if !parts.is_empty() {
-                    let new_cmd = parts[0].to_string();
-                    let mut new_args: Vec<String> = parts[1..].iter().map(|s| s.to_string()).collect();
-                    new_args.extend_from_slice(&command.args);
-                    
-                    let replaced = Command::new(new_cmd, new_args);
-                    command.command = replaced.command;
-                    command.args = replaced.args;
-                    command.command_type = replaced.command_type;
+                let mut new_words: Vec<_> = match tokenizer.tokenize(alias_value) {
+                    Ok(tokens) => tokens
+                        .into_iter()
+                        .filter_map(|t| match t {
+                            Token::Word(w) => Some(w),
+                            Token::Operator(_) | Token::IoNumber(_) | Token::Newline | Token::Comment(_) => None,
+                        })
+                        .collect(),
+                    Err(_) => continue,
+                };
+                if !new_words.is_empty() {
+                    new_words.extend_from_slice(&command.words[1..]);
+                    let assignments = std::mem::take(&mut command.assignments);
+                    let redirects = std::mem::take(&mut command.redirects);
+                    *command = Command::from_words(new_words);
+                    command.assignments.splice(0..0, assignments);
+                    command.redirects = redirects;
                 }
             }
         }
@@ -1,5 +1,12 @@
+pub mod assignment;
+// the module is named like the struct it holds
+#[allow(clippy::module_inception)]
 pub mod command;
 pub mod command_error;
 pub mod command_list;
 pub mod command_type;
 pub mod operator;
+pub mod redirect;
+pub mod span;
+pub mod token;
+pub mod word;
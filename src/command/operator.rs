@@ -1,6 +1,18 @@
-#[derive(Debug, PartialEq)]
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Operator {
     RedirectOverwrite, // >
     RedirectAppend,    // >>
     Pipe,              // |
+    RedirectInput,     // <
+    RedirectDuplicate, // >&
+    RedirectClobber,   // >|, overwrites even with noclobber
+}
+
+impl Operator {
+    pub fn is_redirect(&self) -> bool {
+        *self != Operator::Pipe
+    }
 }
@@ -0,0 +1,35 @@
+use serde::Serialize;
+
+use crate::command::operator::Operator;
+use crate::command::word::Word;
+
+// a redirection like `> out.txt`, `2>> err.log`, `< in.txt` or `2>&1`
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Redirect {
+    pub fd: u32,
+    pub operator: Operator,
+    pub target: Word,
+    // the target after expansion, None if it expanded to zero or several fields
+    #[serde(skip)]
+    pub file: Option<String>,
+}
+
+impl Redirect {
+    pub fn new(fd: Option<u32>, operator: Operator, target: Word) -> Self {
+        Self {
+            fd: fd.unwrap_or(Self::default_fd(operator)),
+            operator,
+            file: Some(target.text()),
+            target,
+        }
+    }
+
+    pub fn is_default_fd(&self) -> bool {
+        self.fd == Self::default_fd(self.operator)
+    }
+
+    // without an explicit fd `<` redirects stdin and everything else stdout
+    fn default_fd(operator: Operator) -> u32 {
+        if operator == Operator::RedirectInput { 0 } else { 1 }
+    }
+}
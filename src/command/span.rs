@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+// where a token is in the input, offsets count characters, line and column start at 1
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
@@ -0,0 +1,14 @@
+use serde::Serialize;
+
+use crate::command::operator::Operator;
+use crate::command::word::Word;
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Token {
+    Word(Word),
+    Operator(Operator),
+    IoNumber(u32), // the 2 in `2>file`
+    Newline,
+    Comment(String),
+}
@@ -0,0 +1,174 @@
+use serde::Serialize;
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum WordPart {
+    Literal(String),             // abc
+    Escaped(char),               // \c
+    SingleQuoted(String),        // 'abc'
+    DoubleQuoted(Vec<WordPart>), // "abc $VAR"
+    Parameter(Parameter),        // $VAR, ${VAR}, $1, $@, $?, ${arr[@]}
+    Tilde,                       // ~
+    Array(Vec<Word>),            // (a b c) in arr=(a b c)
+}
+
+// a parameter expansion like ${#arr[@]}, ${!arr[@]} or ${arr[@]:1:2}
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Parameter {
+    pub name: String,
+    pub subscript: Option<String>,
+    pub length: bool,
+    pub keys: bool,
+    pub slice: Option<(String, Option<String>)>,
+}
+
+impl Parameter {
+    pub fn new(name: &str) -> Self {
+        Parameter {
+            name: name.to_string(),
+            subscript: None,
+            length: false,
+            keys: false,
+            slice: None,
+        }
+    }
+
+    // parses the body of a ${...} expansion
+    pub fn parse(body: &str) -> Option<Self> {
+        let (length, keys, rest) = if body.len() > 1 && body.starts_with('#') {
+            (true, false, &body[1..])
+        } else if body.len() > 1 && body.starts_with('!') {
+            (false, true, &body[1..])
+        } else {
+            (false, false, body)
+        };
+
+        let name_len = if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len())
+        } else if rest.starts_with(['@', '*', '#', '?']) {
+            1
+        } else {
+            rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len())
+        };
+        if name_len == 0 {
+            return None;
+        }
+        let name = &rest[..name_len];
+        let mut rest = &rest[name_len..];
+
+        let mut subscript = None;
+        if rest.starts_with('[') {
+            let end = rest.find(']')?;
+            subscript = Some(rest[1..end].to_string());
+            rest = &rest[end + 1..];
+        }
+
+        // only `:` before an offset is a slice, `:-`, `:=`, `:+` and `:?` are not supported
+        let mut slice = None;
+        if let Some(range) = rest.strip_prefix(':') {
+            if length || keys || !range.starts_with(|c: char| c.is_ascii_digit() || c == ' ' || c == '(') {
+                return None;
+            }
+            slice = match range.split_once(':') {
+                Some((offset, count)) => Some((offset.to_string(), Some(count.to_string()))),
+                None => Some((range.to_string(), None)),
+            };
+            rest = "";
+        }
+
+        // ${!name} is only supported for array keys
+        if !rest.is_empty() || (keys && !matches!(subscript.as_deref(), Some("@") | Some("*"))) {
+            return None;
+        }
+
+        Some(Parameter {
+            name: name.to_string(),
+            subscript,
+            length,
+            keys,
+            slice,
+        })
+    }
+
+    pub fn is_simple(&self) -> bool {
+        self.subscript.is_none() && !self.length && !self.keys && self.slice.is_none()
+    }
+
+    // true if the expansion produces one field per element like "$@" does
+    pub fn is_multi_field(&self) -> bool {
+        match &self.subscript {
+            Some(s) => s == "@" && !self.length,
+            None => self.name == "@" && !self.length,
+        }
+    }
+
+    pub fn source(&self) -> String {
+        if self.is_simple() && (self.name.len() == 1 || !self.name.starts_with(|c: char| c.is_ascii_digit())) {
+            return format!("${}", self.name);
+        }
+        let mut body = String::new();
+        if self.length {
+            body.push('#');
+        }
+        if self.keys {
+            body.push('!');
+        }
+        body.push_str(&self.name);
+        if let Some(subscript) = &self.subscript {
+            body.push_str(&format!("[{}]", subscript));
+        }
+        if let Some((offset, count)) = &self.slice {
+            body.push_str(&format!(":{}", offset));
+            if let Some(count) = count {
+                body.push_str(&format!(":{}", count));
+            }
+        }
+        format!("${{{}}}", body)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+impl Word {
+    pub fn new(parts: Vec<WordPart>) -> Self {
+        Self { parts }
+    }
+
+    pub fn literal(text: &str) -> Self {
+        Self {
+            parts: vec![WordPart::Literal(text.to_string())],
+        }
+    }
+
+    // the word with quotes removed but without any expansion applied
+    pub fn text(&self) -> String {
+        Self::parts_text(&self.parts)
+    }
+
+    // true if the word is a plain unquoted literal (e.g. eligible for alias lookup)
+    pub fn is_plain_literal(&self) -> bool {
+        self.parts.len() == 1 && matches!(self.parts[0], WordPart::Literal(_))
+    }
+
+    fn parts_text(parts: &[WordPart]) -> String {
+        let mut text = String::new();
+        for part in parts {
+            match part {
+                WordPart::Literal(s) | WordPart::SingleQuoted(s) => text.push_str(s),
+                WordPart::Escaped(c) => text.push(*c),
+                WordPart::DoubleQuoted(inner) => text.push_str(&Self::parts_text(inner)),
+                WordPart::Parameter(parameter) => text.push_str(&parameter.source()),
+                WordPart::Tilde => text.push('~'),
+                WordPart::Array(words) => {
+                    let elements: Vec<String> = words.iter().map(|w| w.text()).collect();
+                    text.push_str(&format!("({})", elements.join(" ")));
+                }
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::logger;
+use crate::util;
+
+// a remembered PATH lookup and how often it was used
+#[derive(Debug, PartialEq)]
+pub struct HashEntry {
+    pub path: PathBuf,
+    pub hits: u32,
+}
+
+// caches where commands were found on PATH, so running a command doesn't search every
+// directory again, the cache is cleared when PATH changes
+pub struct CommandHash {
+    pub logger: logger::Logger,
+    entries: HashMap<String, HashEntry>,
+    // the PATH the entries were found with
+    path: Option<OsString>,
+}
+
+impl Default for CommandHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandHash {
+    pub fn new() -> Self {
+        CommandHash {
+            logger: logger::Logger::new(false),
+            entries: HashMap::new(),
+            path: env::var_os("PATH"),
+        }
+    }
+
+    // the path of an executable on PATH, names containing a `/` are never looked up
+    pub fn lookup(&mut self, name: &str) -> Option<PathBuf> {
+        if name.is_empty() || name.contains('/') {
+            return None;
+        }
+        self.check_path();
+
+        if let Some(entry) = self.entries.get_mut(name) {
+            if util::is_executable(&entry.path) {
+                entry.hits += 1;
+                return Some(entry.path.clone());
+            }
+            self.logger.print_debug(
+                String::from("CommandHash"),
+                format!("{} is gone, searching PATH again", entry.path.display()),
+            );
+            self.entries.remove(name);
+        }
+
+        let path = util::find_in_path(name)?;
+        self.logger
+            .print_debug(String::from("CommandHash"), format!("found {} at {}", name, path.display()));
+        self.entries.insert(name.to_string(), HashEntry { path: path.clone(), hits: 1 });
+        Some(path)
+    }
+
+    // where a command would be run from, without remembering it, and whether the path is
+    // a remembered one
+    pub fn find(&mut self, name: &str) -> Option<(PathBuf, bool)> {
+        if name.contains('/') {
+            return util::is_executable(Path::new(name)).then(|| (PathBuf::from(name), false));
+        }
+        self.check_path();
+        if let Some(entry) = self.entries.get(name).filter(|entry| util::is_executable(&entry.path)) {
+            return Some((entry.path.clone(), true));
+        }
+        util::find_in_path(name).map(|path| (path, false))
+    }
+
+    // every path a command could be run from, the remembered one first, then the ones on PATH
+    pub fn find_all(&mut self, name: &str) -> Vec<(PathBuf, bool)> {
+        if name.contains('/') {
+            return self.find(name).into_iter().collect();
+        }
+        self.check_path();
+        let hashed = self.entries.get(name).filter(|entry| util::is_executable(&entry.path));
+        let mut found: Vec<(PathBuf, bool)> = hashed.map(|entry| (entry.path.clone(), true)).into_iter().collect();
+        for path in util::find_all_in_path(name) {
+            if !found.iter().any(|(known, _)| *known == path) {
+                found.push((path, false));
+            }
+        }
+        found
+    }
+
+    // remembers a path for a name without searching, like `hash -p path name`
+    pub fn add(&mut self, name: &str, path: PathBuf) {
+        self.check_path();
+        self.entries.insert(name.to_string(), HashEntry { path, hits: 0 });
+    }
+
+    // searches PATH for the name and remembers it without counting a hit, like `hash name`
+    pub fn remember(&mut self, name: &str) -> bool {
+        let found = self.lookup(name).is_some();
+        if let Some(entry) = self.entries.get_mut(name) {
+            entry.hits -= 1;
+        }
+        found
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // all entries sorted by name
+    pub fn entries(&mut self) -> Vec<(&String, &HashEntry)> {
+        self.check_path();
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|(name, _)| *name);
+        entries
+    }
+
+    fn check_path(&mut self) {
+        let path = env::var_os("PATH");
+        if path != self.path {
+            self.logger
+                .print_debug(String::from("CommandHash"), String::from("PATH changed, clearing the hash table"));
+            self.entries.clear();
+            self.path = path;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vanished_path_is_dropped() {
+        let mut command_hash = CommandHash::new();
+        let path = env::temp_dir().join(format!("fesh-hash-{}", std::process::id()));
+        command_hash.add("fesh-hash-test", path);
+        assert_eq!(command_hash.entries().len(), 1);
+
+        assert_eq!(command_hash.lookup("fesh-hash-test"), None);
+        assert!(command_hash.entries().is_empty());
+        assert_eq!(command_hash.lookup("./fesh-hash-test"), None);
+    }
+}
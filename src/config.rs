@@ -1,3 +1,4 @@
+use crate::options;
 use crate::prompt;
 use serde::Deserialize;
 
@@ -15,6 +16,16 @@ pub struct Config {
     pub history: HistoryConfig,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub options: options::Options,
+    #[serde(default)]
+    pub command_not_found: CommandNotFoundConfig,
+}
+
+#[derive(Deserialize, Default)]
+pub struct CommandNotFoundConfig {
+    // runs instead of the missing command with its name and arguments
+    pub handler: Option<PathBuf>,
 }
 
 #[derive(Deserialize)]
@@ -5,7 +5,7 @@ use std::path::PathBuf;
 use crate::config;
 use crate::logger;
 
-const DEFAULT_CONFIG: &str = r#"
+pub const DEFAULT_CONFIG: &str = r#"
 [prompt]
 text = "$ "
 color = "blue"
@@ -26,6 +26,12 @@ pub struct ConfigParser {
     pub config_file: PathBuf,
 }
 
+impl Default for ConfigParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ConfigParser {
     pub fn new() -> Self {
         ConfigParser {
@@ -0,0 +1,157 @@
+use std::io;
+use std::os::fd::OwnedFd;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::PathBuf;
+use std::process::Command as SysCommand;
+use std::process::ExitStatus;
+use std::process::Stdio;
+
+use nix::libc;
+
+use crate::signals;
+use crate::timing::Usage;
+
+// everything needed to start an external command, the program is already looked up
+#[derive(Debug)]
+pub struct Spawn {
+    pub program: PathBuf,
+    pub arg0: String,
+    pub args: Vec<String>,
+    // set in addition to the environment of the shell, from `name=value cmd`
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<PathBuf>,
+    // stdin, stdout and stderr, None inherits the one of the shell
+    pub fds: [Option<OwnedFd>; 3],
+}
+
+impl Spawn {
+    // the process for the spawn, it starts with the default signal dispositions
+    pub fn command(self) -> SysCommand {
+        let mut cmd = SysCommand::new(&self.program);
+        cmd.arg0(&self.arg0);
+        cmd.args(&self.args);
+        cmd.envs(self.env);
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        // fesh is single-threaded, only async-signal-safe calls happen before exec
+        unsafe {
+            cmd.pre_exec(|| {
+                signals::reset_for_child();
+                Ok(())
+            });
+        }
+        let [stdin, stdout, stderr] = self.fds;
+        if let Some(fd) = stdin {
+            cmd.stdin(Stdio::from(fd));
+        }
+        if let Some(fd) = stdout {
+            cmd.stdout(Stdio::from(fd));
+        }
+        if let Some(fd) = stderr {
+            cmd.stderr(Stdio::from(fd));
+        }
+        cmd
+    }
+}
+
+// starts the external commands of a pipeline and waits for them
+pub trait Executor {
+    // starts the command and returns its process id, the fds of the spawn are closed in
+    // the shell afterwards
+    fn spawn(&mut self, spawn: Spawn) -> io::Result<u32>;
+
+    // waits for a spawned command, returns how it exited and the resources it used
+    fn wait(&mut self, pid: u32) -> io::Result<(ExitStatus, Usage)>;
+}
+
+// runs commands as real child processes
+pub struct ProcessExecutor;
+
+impl Executor for ProcessExecutor {
+    fn spawn(&mut self, spawn: Spawn) -> io::Result<u32> {
+        // the child is waited for by pid, dropping it neither kills nor waits
+        let child = spawn.command().spawn()?;
+        signals::add_child(child.id());
+        Ok(child.id())
+    }
+
+    fn wait(&mut self, pid: u32) -> io::Result<(ExitStatus, Usage)> {
+        let pid = i32::try_from(pid).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        wait_pid(pid)
+    }
+}
+
+// waits for a child process, also used for builtins forked by the shell
+pub fn wait_pid(pid: i32) -> io::Result<(ExitStatus, Usage)> {
+    let mut status = 0;
+    // all fields are plain integers, zero is a valid value
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        if unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) } != -1 {
+            break;
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+    signals::remove_child(pid);
+    Ok((ExitStatus::from_raw(status), Usage::from_rusage(&rusage)))
+}
+
+#[cfg(test)]
+pub use fake::FakeExecutor;
+
+#[cfg(test)]
+mod fake {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct State {
+        spawned: Vec<Spawn>,
+        exit_codes: HashMap<String, i32>,
+    }
+
+    // records what would be spawned instead of starting processes, clones share the records
+    // so a test keeps one while the shell owns the other, the fds of every spawn are kept
+    // open so a test can check where they lead
+    #[derive(Clone, Default)]
+    pub struct FakeExecutor {
+        state: Rc<RefCell<State>>,
+    }
+
+    impl FakeExecutor {
+        pub fn new() -> Self {
+            FakeExecutor::default()
+        }
+
+        // commands with this arg0 exit with the code, all others with 0
+        pub fn exit_with(&self, arg0: &str, code: i32) {
+            self.state.borrow_mut().exit_codes.insert(arg0.to_string(), code);
+        }
+
+        pub fn take_spawned(&self) -> Vec<Spawn> {
+            std::mem::take(&mut self.state.borrow_mut().spawned)
+        }
+    }
+
+    impl Executor for FakeExecutor {
+        fn spawn(&mut self, spawn: Spawn) -> io::Result<u32> {
+            let mut state = self.state.borrow_mut();
+            state.spawned.push(spawn);
+            Ok(state.spawned.len() as u32)
+        }
+
+        fn wait(&mut self, pid: u32) -> io::Result<(ExitStatus, Usage)> {
+            let state = self.state.borrow();
+            let spawn = state.spawned.get(pid as usize - 1).ok_or(io::ErrorKind::NotFound)?;
+            let code = state.exit_codes.get(&spawn.arg0).copied().unwrap_or(0);
+            Ok((ExitStatus::from_raw(code << 8), Usage::default()))
+        }
+    }
+}
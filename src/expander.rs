@@ -0,0 +1,657 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+
+use crate::command::assignment::{Assignment, AssignmentValue};
+use crate::command::command::Command;
+use crate::command::command_list::CommandList;
+use crate::command::token::Token;
+use crate::command::word::{Parameter, Word, WordPart};
+use crate::logger;
+use crate::tokenizer;
+use crate::variables::{Value, Variables};
+
+pub const DEFAULT_IFS: &str = " \t\n";
+
+// a piece of an expanded word, before field splitting
+#[derive(Debug, PartialEq)]
+enum Segment {
+    // text from literals and quotes, never split
+    Fixed(String),
+    // unquoted expansion result, subject to field splitting
+    Split(String),
+    // ends the current field, used for "$@"
+    Break,
+}
+
+// the result of a parameter expansion
+enum Expansion {
+    Single(String),
+    // one value per element, `joined` values are joined into one field when quoted ("$*")
+    Fields { values: Vec<String>, joined: bool },
+}
+
+pub struct Expander {
+    pub logger: logger::Logger,
+    pub variables: Variables,
+    pub nounset: bool, // set -u, expanding an unset variable is an error
+    // the first unset variable expanded since the last take_unbound
+    unbound: RefCell<Option<String>>,
+}
+
+impl Default for Expander {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Expander {
+    pub fn new() -> Self {
+        Expander {
+            logger: logger::Logger::new(false),
+            variables: Variables::new(),
+            nounset: false,
+            unbound: RefCell::new(None),
+        }
+    }
+
+    pub fn expand_command_list(&self, command_list: &mut CommandList) {
+        self.unbound.replace(None);
+        for command in command_list.commands.iter_mut() {
+            self.expand_command(command);
+        }
+        self.logger.print_debug(
+            String::from("Expander"),
+            format!("expanded command list: {:?}", command_list),
+        );
+    }
+
+    pub fn expand_command(&self, command: &mut Command) {
+        let fields = self.expand_words(&command.words);
+        command.set_fields(fields);
+        // a redirect target must expand to exactly one field
+        for redirect in command.redirects.iter_mut() {
+            redirect.file = match self.expand_word(&redirect.target).as_slice() {
+                [file] => Some(file.clone()),
+                _ => None,
+            };
+        }
+    }
+
+    pub fn expand_words(&self, words: &[Word]) -> Vec<String> {
+        words.iter().flat_map(|word| self.expand_word(word)).collect()
+    }
+
+    // expands a single word into zero or more fields
+    pub fn expand_word(&self, word: &Word) -> Vec<String> {
+        Self::split_fields(&self.segments(word), &self.ifs())
+    }
+
+    // expands a word into exactly one string without field splitting, like assignment values
+    pub fn expand_word_unsplit(&self, word: &Word) -> String {
+        let mut text = String::new();
+        for segment in self.segments(word) {
+            match segment {
+                Segment::Fixed(s) | Segment::Split(s) => text.push_str(&s),
+                Segment::Break => text.push(' '),
+            }
+        }
+        text
+    }
+
+    // expands source text like an array subscript into one string
+    pub fn expand_text(&self, text: &str) -> String {
+        let words: Vec<Word> = match tokenizer::Tokenizer::new().tokenize(text) {
+            Ok(tokens) => tokens
+                .into_iter()
+                .filter_map(|t| match t {
+                    Token::Word(w) => Some(w),
+                    Token::Operator(_) | Token::IoNumber(_) | Token::Newline | Token::Comment(_) => None,
+                })
+                .collect(),
+            Err(_) => return text.to_string(),
+        };
+        let texts: Vec<String> = words.iter().map(|w| self.expand_word_unsplit(w)).collect();
+        texts.join(" ")
+    }
+
+    // with set -u, fails if an unset variable was expanded since the last call
+    pub fn take_unbound(&self) -> Result<(), String> {
+        match self.unbound.take() {
+            Some(name) => Err(format!("{name}: unbound variable")),
+            None => Ok(()),
+        }
+    }
+
+    fn check_set(&self, name: &str, is_set: bool) {
+        if self.nounset && !is_set {
+            self.unbound.borrow_mut().get_or_insert_with(|| name.to_string());
+        }
+    }
+
+    // expands the value of an assignment and stores it in the variable table
+    pub fn apply_assignment(&mut self, assignment: &Assignment) -> Result<(), String> {
+        let result = self.assign(assignment);
+        self.take_unbound()?;
+        result
+    }
+
+    fn assign(&mut self, assignment: &Assignment) -> Result<(), String> {
+        let name = &assignment.name;
+        match &assignment.value {
+            AssignmentValue::Scalar(word) => {
+                let mut value = self.expand_word_unsplit(word);
+                match &assignment.subscript {
+                    Some(subscript) => {
+                        let key = self.expand_subscript(name, &self.expand_word_unsplit(subscript));
+                        if assignment.append {
+                            value = self.variables.get_element(name, &key).unwrap_or_default() + &value;
+                        }
+                        self.variables.set_element(name, &key, value)?;
+                    }
+                    None => {
+                        if assignment.append {
+                            value = self.variables.get_scalar(name).unwrap_or_default() + &value;
+                        }
+                        self.variables.set_scalar(name, value);
+                    }
+                }
+            }
+            AssignmentValue::Array(elements) => {
+                let associative = self.variables.is_associative(name);
+                let value = self.expand_array(name, elements, associative)?;
+                match (value, assignment.append) {
+                    (Value::Indexed(map), true) => {
+                        self.variables.append_elements(name, map.into_values().collect());
+                    }
+                    (Value::Associative(map), true) => {
+                        for (key, value) in map {
+                            self.variables.set_element(name, &key, value)?;
+                        }
+                    }
+                    (value, _) => self.variables.set(name, value),
+                }
+            }
+        }
+        self.logger.print_debug(
+            String::from("Expander"),
+            format!("assigned {}: {:?}", name, self.variables.get(name)),
+        );
+        Ok(())
+    }
+
+    // expands the elements of an array literal like (a b [5]=c)
+    pub fn expand_array(&self, name: &str, elements: &[Word], associative: bool) -> Result<Value, String> {
+        let mut indexed: BTreeMap<usize, String> = BTreeMap::new();
+        let mut keyed: BTreeMap<String, String> = BTreeMap::new();
+        let mut next = 0;
+
+        for element in elements {
+            match Assignment::parse_element(element) {
+                Some((key, value)) => {
+                    let key = self.expand_word_unsplit(&key);
+                    let value = self.expand_word_unsplit(&value);
+                    if associative {
+                        keyed.insert(key, value);
+                    } else {
+                        let index = self.evaluate_index(&key).filter(|i| *i >= 0).ok_or(format!("{}: bad array subscript", key))?;
+                        indexed.insert(index as usize, value);
+                        next = index as usize + 1;
+                    }
+                }
+                None if associative => {
+                    return Err(format!("{}: {}: must use subscript when assigning associative array", name, element.text()));
+                }
+                None => {
+                    for field in self.expand_word(element) {
+                        indexed.insert(next, field);
+                        next += 1;
+                    }
+                }
+            }
+        }
+
+        if associative {
+            Ok(Value::Associative(keyed))
+        } else {
+            Ok(Value::Indexed(indexed))
+        }
+    }
+
+    fn segments(&self, word: &Word) -> Vec<Segment> {
+        let mut segments: Vec<Segment> = Vec::new();
+        for (i, part) in word.parts.iter().enumerate() {
+            self.expand_part(part, i == 0, &mut segments);
+        }
+        segments
+    }
+
+    fn expand_part(&self, part: &WordPart, first: bool, segments: &mut Vec<Segment>) {
+        match part {
+            WordPart::Literal(s) | WordPart::SingleQuoted(s) => segments.push(Segment::Fixed(s.clone())),
+            WordPart::Escaped(c) => segments.push(Segment::Fixed(c.to_string())),
+            WordPart::Tilde if first => {
+                segments.push(Segment::Fixed(env::var("HOME").unwrap_or_else(|_| String::from("~"))))
+            }
+            WordPart::Tilde => segments.push(Segment::Fixed(String::from("~"))),
+            WordPart::Parameter(parameter) => match self.expand_parameter(parameter) {
+                Expansion::Single(value) => segments.push(Segment::Split(value)),
+                // unquoted $@ and $* both expand to one split field per element
+                Expansion::Fields { values, .. } => {
+                    for (i, value) in values.into_iter().enumerate() {
+                        if i > 0 {
+                            segments.push(Segment::Break);
+                        }
+                        segments.push(Segment::Split(value));
+                    }
+                }
+            },
+            WordPart::DoubleQuoted(inner) => self.expand_double_quoted(inner, segments),
+            // array literals are only valid in assignments
+            WordPart::Array(_) => segments.push(Segment::Fixed(Word::new(vec![part.clone()]).text())),
+        }
+    }
+
+    fn expand_double_quoted(&self, parts: &[WordPart], segments: &mut Vec<Segment>) {
+        // "" is still an (empty) field, unless it only holds "$@"-like expansions without elements
+        let only_multi = !parts.is_empty()
+            && parts
+                .iter()
+                .all(|p| matches!(p, WordPart::Parameter(parameter) if parameter.is_multi_field()));
+        if !only_multi {
+            segments.push(Segment::Fixed(String::new()));
+        }
+
+        for part in parts {
+            match part {
+                WordPart::Parameter(parameter) => match self.expand_parameter(parameter) {
+                    Expansion::Single(value) => segments.push(Segment::Fixed(value)),
+                    Expansion::Fields { values, joined: true } => {
+                        let separator = match self.ifs().chars().next() {
+                            Some(c) => c.to_string(),
+                            None => String::new(),
+                        };
+                        segments.push(Segment::Fixed(values.join(&separator)));
+                    }
+                    Expansion::Fields { values, joined: false } => {
+                        for (i, value) in values.into_iter().enumerate() {
+                            if i > 0 {
+                                segments.push(Segment::Break);
+                            }
+                            segments.push(Segment::Fixed(value));
+                        }
+                    }
+                },
+                WordPart::Literal(s) | WordPart::SingleQuoted(s) => segments.push(Segment::Fixed(s.clone())),
+                WordPart::Escaped(c) => segments.push(Segment::Fixed(c.to_string())),
+                WordPart::Tilde => segments.push(Segment::Fixed(String::from("~"))),
+                WordPart::DoubleQuoted(inner) => self.expand_double_quoted(inner, segments),
+                WordPart::Array(_) => segments.push(Segment::Fixed(Word::new(vec![part.clone()]).text())),
+            }
+        }
+    }
+
+    fn expand_parameter(&self, parameter: &Parameter) -> Expansion {
+        let name = parameter.name.as_str();
+        let is_positional = name == "@" || name == "*";
+
+        let mut expansion = if parameter.keys {
+            let keys = self.variables.get(name).map(|v| v.keys()).unwrap_or_default();
+            Expansion::Fields {
+                values: keys,
+                joined: parameter.subscript.as_deref() == Some("*"),
+            }
+        } else {
+            match parameter.subscript.as_deref() {
+                Some(all @ ("@" | "*")) => Expansion::Fields {
+                    values: match self.variables.get(name) {
+                        Some(value) => value.values(),
+                        None => self.variables.get_scalar(name).into_iter().collect(),
+                    },
+                    joined: all == "*",
+                },
+                Some(subscript) => {
+                    let key = self.expand_subscript(name, &self.expand_text(subscript));
+                    let value = self.variables.get_element(name, &key);
+                    self.check_set(&format!("{name}[{key}]"), value.is_some());
+                    Expansion::Single(value.unwrap_or_default())
+                }
+                None if is_positional => Expansion::Fields {
+                    values: self.variables.positional_params.clone(),
+                    joined: name == "*",
+                },
+                None => {
+                    self.check_set(name, self.is_set(name));
+                    Expansion::Single(self.lookup(name))
+                }
+            }
+        };
+
+        if let Some((offset, count)) = &parameter.slice {
+            let offset = self.evaluate_index(&self.expand_text(offset)).unwrap_or(0);
+            let count = count.as_ref().map(|c| self.evaluate_index(&self.expand_text(c)).unwrap_or(0));
+            expansion = match expansion {
+                Expansion::Single(value) => {
+                    let chars: Vec<char> = value.chars().collect();
+                    Expansion::Single(Self::slice(&chars, offset, count).iter().collect())
+                }
+                Expansion::Fields { values, joined } => {
+                    // slices of $@ start counting at $0
+                    let values = if is_positional && parameter.subscript.is_none() {
+                        std::iter::once(String::from("fesh")).chain(values).collect()
+                    } else {
+                        values
+                    };
+                    Expansion::Fields {
+                        values: Self::slice(&values, offset, count).to_vec(),
+                        joined,
+                    }
+                }
+            };
+        }
+
+        if parameter.length {
+            let length = match expansion {
+                Expansion::Single(value) => value.chars().count(),
+                Expansion::Fields { values, .. } => values.len(),
+            };
+            expansion = Expansion::Single(length.to_string());
+        }
+
+        expansion
+    }
+
+    // negative offsets count from the end, a negative count stops that many elements before the end
+    fn slice<T>(items: &[T], offset: i64, count: Option<i64>) -> &[T] {
+        let len = items.len() as i64;
+        let start = if offset < 0 { len.saturating_add(offset).max(0) } else { offset.min(len) };
+        let end = match count {
+            Some(count) if count < 0 => len.saturating_add(count).max(start),
+            Some(count) => start.saturating_add(count).min(len),
+            None => len,
+        };
+        &items[start as usize..end as usize]
+    }
+
+    // associative arrays use the subscript as key, indexed arrays evaluate it as index
+    pub fn expand_subscript(&self, name: &str, subscript: &str) -> String {
+        if self.variables.is_associative(name) {
+            return subscript.to_string();
+        }
+        self.evaluate_index(subscript).unwrap_or(0).to_string()
+    }
+
+    // evaluates an index, which is either a number or the name of a variable holding one
+    fn evaluate_index(&self, text: &str) -> Option<i64> {
+        let text = text.trim();
+        if let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+            return self.evaluate_index(inner);
+        }
+        if let Ok(index) = text.parse::<i64>() {
+            return Some(index);
+        }
+        if tokenizer::Tokenizer::is_name(text) {
+            return Some(self.variables.get_scalar(text).and_then(|v| v.trim().parse().ok()).unwrap_or(0));
+        }
+        None
+    }
+
+    fn is_set(&self, name: &str) -> bool {
+        if let Ok(index) = name.parse::<usize>() {
+            return index <= self.variables.positional_params.len();
+        }
+        name == "#" || name == "?" || self.variables.get_scalar(name).is_some()
+    }
+
+    fn lookup(&self, name: &str) -> String {
+        let positional_params = &self.variables.positional_params;
+        if let Ok(index) = name.parse::<usize>() {
+            if index == 0 {
+                return String::from("fesh");
+            }
+            return positional_params.get(index - 1).cloned().unwrap_or_default();
+        }
+        if name == "#" {
+            return positional_params.len().to_string();
+        }
+        if name == "?" {
+            return self.variables.last_status.to_string();
+        }
+        self.variables.get_scalar(name).unwrap_or_default()
+    }
+
+    fn ifs(&self) -> String {
+        self.variables.get_scalar("IFS").unwrap_or_else(|| String::from(DEFAULT_IFS))
+    }
+
+    // POSIX field splitting: runs of IFS whitespace delimit fields and are trimmed at the
+    // edges, every other IFS character delimits exactly one field (absorbing adjacent
+    // IFS whitespace), unquoted empty results produce no field at all
+    fn split_fields(segments: &[Segment], ifs: &str) -> Vec<String> {
+        let mut fields: Vec<String> = Vec::new();
+        let mut current = String::new();
+        // the current field exists even if it is still empty (e.g. from "")
+        let mut started = false;
+        // the previous field was ended by IFS whitespace
+        let mut after_whitespace = false;
+
+        for segment in segments {
+            match segment {
+                Segment::Fixed(text) => {
+                    current.push_str(text);
+                    started = true;
+                    after_whitespace = false;
+                }
+                Segment::Break => {
+                    if started {
+                        fields.push(std::mem::take(&mut current));
+                        started = false;
+                    }
+                    after_whitespace = false;
+                }
+                Segment::Split(text) => {
+                    for c in text.chars() {
+                        if !ifs.contains(c) {
+                            current.push(c);
+                            started = true;
+                            after_whitespace = false;
+                        } else if c.is_whitespace() {
+                            if started {
+                                fields.push(std::mem::take(&mut current));
+                                started = false;
+                                after_whitespace = true;
+                            }
+                        } else {
+                            if started || !after_whitespace {
+                                fields.push(std::mem::take(&mut current));
+                                started = false;
+                            }
+                            after_whitespace = false;
+                        }
+                    }
+                }
+            }
+        }
+
+        if started {
+            fields.push(current);
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str, ifs: &str) -> Vec<String> {
+        Expander::split_fields(&[Segment::Split(String::from(text))], ifs)
+    }
+
+    #[test]
+    fn test_split_whitespace() {
+        assert_eq!(split("  -l   -a ", DEFAULT_IFS), vec!["-l", "-a"]);
+    }
+
+    #[test]
+    fn test_split_empty_is_removed() {
+        assert!(split("", DEFAULT_IFS).is_empty());
+        assert!(split("   ", DEFAULT_IFS).is_empty());
+    }
+
+    #[test]
+    fn test_split_non_whitespace() {
+        assert_eq!(split("a::b:", ":"), vec!["a", "", "b"]);
+        assert_eq!(split(":a", ":"), vec!["", "a"]);
+    }
+
+    #[test]
+    fn test_split_mixed_ifs() {
+        assert_eq!(split(" a : b ", " :"), vec!["a", "b"]);
+        assert_eq!(split("a : : b", " :"), vec!["a", "", "b"]);
+    }
+
+    #[test]
+    fn test_split_empty_ifs() {
+        assert_eq!(split(" a b ", ""), vec![" a b "]);
+    }
+
+    #[test]
+    fn test_quoted_empty_is_kept() {
+        let segments = [Segment::Fixed(String::new()), Segment::Split(String::new())];
+        assert_eq!(Expander::split_fields(&segments, DEFAULT_IFS), vec![""]);
+    }
+
+    #[test]
+    fn test_split_joins_fixed_text() {
+        let segments = [
+            Segment::Fixed(String::from("pre")),
+            Segment::Split(String::from("a b")),
+            Segment::Fixed(String::from("post")),
+        ];
+        assert_eq!(Expander::split_fields(&segments, DEFAULT_IFS), vec!["prea", "bpost"]);
+    }
+
+    #[test]
+    fn test_quoted_at_keeps_parameters() {
+        let mut expander = Expander::new();
+        expander.variables.positional_params = vec![String::from("a b"), String::new(), String::from("c")];
+        let word = Word::new(vec![WordPart::DoubleQuoted(vec![WordPart::Parameter(Parameter::new("@"))])]);
+        assert_eq!(expander.expand_word(&word), vec!["a b", "", "c"]);
+    }
+
+    #[test]
+    fn test_quoted_at_without_parameters() {
+        let expander = Expander::new();
+        let word = Word::new(vec![WordPart::DoubleQuoted(vec![WordPart::Parameter(Parameter::new("@"))])]);
+        assert!(expander.expand_word(&word).is_empty());
+    }
+
+    #[test]
+    fn test_quoted_at_with_prefix_and_suffix() {
+        let mut expander = Expander::new();
+        expander.variables.positional_params = vec![String::from("a"), String::from("b")];
+        let word = Word::new(vec![WordPart::DoubleQuoted(vec![
+            WordPart::Literal(String::from("x")),
+            WordPart::Parameter(Parameter::new("@")),
+            WordPart::Literal(String::from("y")),
+        ])]);
+        assert_eq!(expander.expand_word(&word), vec!["xa", "by"]);
+    }
+
+    #[test]
+    fn test_unquoted_at_is_split() {
+        let mut expander = Expander::new();
+        expander.variables.positional_params = vec![String::from("a b"), String::new(), String::from("c")];
+        let word = Word::new(vec![WordPart::Parameter(Parameter::new("@"))]);
+        assert_eq!(expander.expand_word(&word), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_last_status() {
+        let mut expander = Expander::new();
+        expander.variables.last_status = 127;
+        assert_eq!(expander.expand_word(&parse_word("$?")), vec!["127"]);
+        assert_eq!(expander.expand_word(&parse_word("\"${?}x\"")), vec!["127x"]);
+    }
+
+    #[test]
+    fn test_nounset() {
+        let mut expander = Expander::new();
+        expander.nounset = true;
+        expander.variables.set_scalar("FESH_TEST_SET", String::new());
+        expander.variables.positional_params = vec![String::from("a")];
+        expander.expand_word(&parse_word("$FESH_TEST_SET $1 $# \"$@\" ${FESH_TEST_ARR[@]}"));
+        assert_eq!(expander.take_unbound(), Ok(()));
+
+        expander.expand_word(&parse_word("$2${FESH_TEST_UNSET}"));
+        assert_eq!(expander.take_unbound(), Err(String::from("2: unbound variable")));
+        assert_eq!(expander.take_unbound(), Ok(()));
+
+        assign(&mut expander, "FESH_TEST_ARR=(x)");
+        expander.expand_word(&parse_word("${FESH_TEST_ARR[1]}"));
+        assert_eq!(expander.take_unbound(), Err(String::from("FESH_TEST_ARR[1]: unbound variable")));
+    }
+
+    fn parse_word(text: &str) -> Word {
+        match tokenizer::Tokenizer::new().tokenize(text).unwrap().remove(0) {
+            Token::Word(word) => word,
+            Token::Operator(_) | Token::IoNumber(_) | Token::Newline | Token::Comment(_) => panic!("expected a word"),
+        }
+    }
+
+    fn assign(expander: &mut Expander, text: &str) {
+        let assignment = Assignment::parse(&parse_word(text)).unwrap();
+        expander.apply_assignment(&assignment).unwrap();
+    }
+
+    #[test]
+    fn test_indexed_array() {
+        let mut expander = Expander::new();
+        assign(&mut expander, "arr=(a 'b c' d)");
+        assign(&mut expander, "arr[5]=x");
+        assign(&mut expander, "arr+=(y)");
+        assert_eq!(expander.expand_word(&parse_word("\"${arr[@]}\"")), vec!["a", "b c", "d", "x", "y"]);
+        assert_eq!(expander.expand_word(&parse_word("${arr[@]}")), vec!["a", "b", "c", "d", "x", "y"]);
+        assert_eq!(expander.expand_word(&parse_word("${#arr[@]}")), vec!["5"]);
+        assert_eq!(expander.expand_word(&parse_word("${!arr[@]}")), vec!["0", "1", "2", "5", "6"]);
+        assert_eq!(expander.expand_word(&parse_word("${arr[-1]}")), vec!["y"]);
+        assert_eq!(expander.expand_word(&parse_word("\"${arr[@]:1:2}\"")), vec!["b c", "d"]);
+        assert_eq!(expander.expand_word(&parse_word("$arr")), vec!["a"]);
+    }
+
+    #[test]
+    fn test_associative_array() {
+        let mut expander = Expander::new();
+        expander.variables.set("m", Value::Associative(BTreeMap::new()));
+        assign(&mut expander, "m=([one]=1 [two]='2 2')");
+        assign(&mut expander, "m[three]=3");
+        assert_eq!(expander.expand_word(&parse_word("\"${m[two]}\"")), vec!["2 2"]);
+        assert_eq!(expander.expand_word(&parse_word("${!m[@]}")), vec!["one", "three", "two"]);
+        assert_eq!(expander.expand_word(&parse_word("${#m[@]}")), vec!["3"]);
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut expander = Expander::new();
+        assign(&mut expander, "s=abcdef");
+        assert_eq!(expander.expand_word(&parse_word("${s:1:2}")), vec!["bc"]);
+        assert_eq!(expander.expand_word(&parse_word("\"${s: -2}\"")), vec!["ef"]);
+        assert_eq!(expander.expand_word(&parse_word("${s:(-3):1}")), vec!["d"]);
+
+        // the other `:` operators are not slices with a negative offset
+        let tokenizer = tokenizer::Tokenizer::new();
+        for text in ["${NOPE:-default}", "${HOME:+alt}", "${x:-2}", "${x:=y}", "${x:?}", "${x:}"] {
+            let error = crate::command::command_error::CommandError::BadSubstitution(text.to_string());
+            assert_eq!(tokenizer.tokenize(text), Err(error));
+        }
+    }
+
+    #[test]
+    fn test_empty_array_in_quotes() {
+        let mut expander = Expander::new();
+        assign(&mut expander, "arr=()");
+        assert!(expander.expand_word(&parse_word("\"${arr[@]}\"")).is_empty());
+        assert_eq!(expander.expand_word(&parse_word("\"${arr[*]}\"")), vec![""]);
+    }
+}
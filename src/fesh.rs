@@ -1,23 +1,100 @@
 use std::env;
-use std::path;
-use std::process::Command as SysCommand;
-use std::process::Stdio;
-use std::process::exit;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{self, PathBuf};
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
 
+use nix::libc;
+use nix::sys::stat::{self, Mode};
+use nix::sys::signal::Signal;
+use nix::unistd::{ForkResult, Pid, fork};
+
+use crate::builtin_io::{BuiltinIo, Output};
+use crate::command::assignment::{Assignment, AssignmentValue};
 use crate::command::command;
-use crate::command::command_list::CommandList;
+use crate::command::command_list::{CommandList, Time};
 use crate::command::command_type::CommandType;
 use crate::command::operator::Operator;
+use crate::command::redirect::Redirect;
+use crate::command::word::Word;
+use crate::command_hash;
 use crate::config;
+use crate::executor::{self, Executor, ProcessExecutor, Spawn};
+use crate::expander;
 use crate::file_writer;
 use crate::input_parser;
 use crate::input_reader;
+use crate::input_reader::Line;
+use crate::limits::{self, Limit};
 use crate::logger;
+use crate::mode;
+use crate::options;
+use crate::read_line::{self, End, ReadOptions};
+use crate::signals;
+use crate::timing::{self, Usage};
+use crate::tokenizer::Tokenizer;
+use crate::traps::{self, Condition};
+use crate::util;
+use crate::variables::{Saved, Value};
+
+// exit statuses, following bash
+const STATUS_USAGE: i32 = 2; // syntax errors and invalid builtin usage
+const STATUS_NOT_EXECUTABLE: i32 = 126;
+const STATUS_NOT_FOUND: i32 = 127;
+const STATUS_SIGNAL_BASE: i32 = 128; // a child killed by signal N exits with 128 + N
+
+// how many similar commands are suggested for a command that was not found
+const MAX_SUGGESTIONS: usize = 3;
+
+// how deep `source` can nest, a file sourcing itself stops here
+const MAX_SOURCE_DEPTH: usize = 100;
+
+// prefix of the commands printed by set -x
+const DEFAULT_PS4: &str = "+ ";
+
+// what a command name runs, see Fesh::resolve
+enum Resolution {
+    Keyword,
+    Alias(String),
+    Builtin,
+    File { path: PathBuf, hashed: bool },
+}
+
+// the output styles of type, command -v and which
+#[derive(Clone, Copy, PartialEq)]
+enum Describe {
+    Type,      // type name, command -V name
+    TypeShort, // type -t name
+    Command,   // command -v name
+    Which,     // which [-a] name
+}
+
+// a running stage of a pipeline
+enum Stage {
+    Process(u32), // started by the executor
+    Forked(Pid),  // a builtin
+}
 
 pub struct Fesh<'a> {
     config: &'a config::Config,
     input_reader: input_reader::InputReader<'a>,
     input_parser: input_parser::InputParser,
+    expander: expander::Expander,
+    command_hash: command_hash::CommandHash,
+    executor: Box<dyn Executor>,
+    options: options::Options,
+    traps: traps::Traps,
+    in_trap: bool, // ERR and DEBUG don't trigger while a trap runs
+    // set by `exit`, `set -e` and `set -u`, every loop running commands stops before the next
+    exiting: Option<i32>,
+    // where `set -x` traces commands, the stderr of the shell
+    xtrace_output: Output,
+    source_depth: usize,
+    interactive: bool,
     file_writer: file_writer::FileWriter,
     logger: logger::Logger,
 }
@@ -29,238 +106,2007 @@ impl<'a> Fesh<'a> {
             unsafe { env::set_var(key, value) };
         }
 
+        let mut expander = expander::Expander::new();
+        expander.nounset = config.options.nounset;
+
         Fesh {
-            config: config,
+            config,
             input_reader: input_reader::InputReader::new(&config.readline, &config.history),
             input_parser: input_parser::InputParser::new(),
+            expander,
+            command_hash: command_hash::CommandHash::new(),
+            executor: Box::new(ProcessExecutor),
+            options: config.options.clone(),
+            traps: traps::Traps::new(),
+            in_trap: false,
+            exiting: None,
+            xtrace_output: Output::Stderr,
+            source_depth: 0,
+            interactive: false,
             file_writer: file_writer::FileWriter::new(),
             logger: logger::Logger::new(false),
         }
     }
 
-    pub fn run(&mut self) {
+    // runs the interactive loop, returns the exit status of the last command at the end of input
+    pub fn run(&mut self) -> i32 {
+        signals::init(true);
+        self.interactive = true;
         loop {
-            let input: String = self.input_reader.readline(&self.config.prompt);
-            let mut command_list: CommandList = match self.input_parser.parse(input) {
+            self.run_pending_traps();
+            if let Some(status) = self.exiting {
+                return status;
+            }
+            let input: String = match self.input_reader.readline(&self.config.prompt) {
+                Line::Input(input) => input,
+                // readline catches ^C itself, so a trap on SIGINT is run here
+                Line::Interrupted => {
+                    self.set_last_status(STATUS_SIGNAL_BASE + Signal::SIGINT as i32);
+                    self.run_trap(Condition::Signal(Signal::SIGINT));
+                    continue;
+                }
+                Line::Eof => {
+                    self.run_exit_trap();
+                    return self.last_status();
+                }
+            };
+            // a line can hold several command lists like `cd src; ls`
+            let command_lists = match self.input_parser.parse_script(&input) {
                 Ok(c) => c,
-                Err(_) => continue,
+                Err(e) => {
+                    self.logger.print_error(format!("syntax error: {e}"));
+                    self.set_last_status(STATUS_USAGE);
+                    continue;
+                }
             };
 
-            self.logger.print_debug(
-                String::from("Fesh"),
-                format!("command list before aliases: {:?}", command_list),
-            );
+            for command_list in command_lists {
+                self.execute_line(command_list);
+                if let Some(status) = self.exiting {
+                    return status;
+                }
+                self.run_pending_traps();
+                // ^C stops the rest of the line as well
+                if self.last_status() == STATUS_SIGNAL_BASE + Signal::SIGINT as i32 {
+                    break;
+                }
+            }
 
-            command_list.replace_aliases(&self.config.aliases);
+            // the terminal is gone, the children of the shell got SIGHUP as well
+            if signals::hangup_received() {
+                self.run_exit_trap();
+                return STATUS_SIGNAL_BASE + Signal::SIGHUP as i32;
+            }
+            // start the prompt on a new line after ^C
+            if self.last_status() == STATUS_SIGNAL_BASE + Signal::SIGINT as i32 {
+                println!();
+            }
+        }
+    }
 
-            self.logger.print_debug(
-                String::from("Fesh"),
-                format!("command list after aliases: {:?}", command_list),
-            );
+    // runs a script non-interactively, e.g. `fesh -c 'ls'` or `fesh script.fesh`,
+    // returns the exit status of the last command
+    pub fn run_script(&mut self, input: &str, args: Vec<String>) -> i32 {
+        signals::init(false);
+        self.expander.variables.positional_params = args;
+
+        let command_lists = match self.input_parser.parse_script(input) {
+            Ok(c) => c,
+            Err(e) => {
+                self.logger.print_error(format!("syntax error: {e}"));
+                return STATUS_USAGE;
+            }
+        };
+
+        for command_list in command_lists {
+            self.execute_line(command_list);
+            if let Some(status) = self.exiting {
+                return status;
+            }
+            self.run_pending_traps();
+        }
+        self.run_exit_trap();
+        self.exiting.unwrap_or(self.last_status())
+    }
+
+    fn last_status(&self) -> i32 {
+        self.expander.variables.last_status
+    }
+
+    fn set_last_status(&mut self, status: i32) {
+        self.expander.variables.last_status = status;
+    }
+
+    // runs the EXIT trap and makes the shell exit with the status once the running commands
+    // returned, `exit` in the EXIT trap replaces the status
+    fn exit_shell(&mut self, status: i32) {
+        self.set_last_status(status);
+        self.run_exit_trap();
+        self.exiting.get_or_insert(status);
+    }
+
+    // the EXIT trap only runs once, even if it exits itself
+    fn run_exit_trap(&mut self) {
+        if let Some(action) = self.traps.take_exit() {
+            self.run_action(&action);
+        }
+    }
+
+    // runs the traps of the signals that arrived since the last safe point, children that
+    // exited in the meantime without being waited for are reaped here as well
+    fn run_pending_traps(&mut self) {
+        signals::reap_children();
+        for sig in signals::take_pending() {
+            self.run_trap(Condition::Signal(sig));
+        }
+    }
+
+    fn run_trap(&mut self, condition: Condition) {
+        if let Some(action) = self.traps.get(condition).filter(|action| !action.is_empty()) {
+            let action = action.to_string();
+            self.run_action(&action);
+        }
+    }
+
+    // runs the command of a trap like a script, `$?` is kept for the interrupted code
+    fn run_action(&mut self, action: &str) {
+        let status = self.last_status();
+        let in_trap = std::mem::replace(&mut self.in_trap, true);
+        match self.input_parser.parse_script(action) {
+            Ok(command_lists) => {
+                for command_list in command_lists {
+                    self.execute_line(command_list);
+                    if self.exiting.is_some() {
+                        break;
+                    }
+                }
+            }
+            Err(e) => self.logger.print_error(format!("trap: syntax error: {e}")),
+        }
+        self.in_trap = in_trap;
+        self.set_last_status(status);
+    }
+
+    // replaces aliases, expands and executes one parsed line
+    fn execute_line(&mut self, mut command_list: CommandList) {
+        self.logger.print_debug(
+            String::from("Fesh"),
+            format!("command list before aliases: {:?}", command_list),
+        );
 
-            self.execute_command_list(command_list);
+        command_list.replace_aliases(&self.config.aliases);
+
+        self.logger.print_debug(
+            String::from("Fesh"),
+            format!("command list after aliases: {:?}", command_list),
+        );
+
+        self.expander.expand_command_list(&mut command_list);
+        // set -u, a script stops at the first unset variable
+        if let Err(e) = self.expander.take_unbound() {
+            self.logger.print_error(e);
+            self.set_last_status(1);
+            if !self.interactive {
+                self.exit_shell(1);
+            }
+            return;
+        }
+        for command in command_list.commands.iter_mut() {
+            command.strip_command_prefix();
+        }
+        if !self.in_trap {
+            self.run_trap(Condition::Debug);
+        }
+        if self.options.xtrace {
+            self.trace(&command_list);
+        }
+
+        let negated = command_list.negated;
+        let status = self.execute_command_list(command_list);
+        self.set_last_status(status);
+        if self.exiting.is_some() {
+            return;
+        }
+
+        // the status of a negated pipeline is never checked by ERR and set -e
+        if status != 0 && !negated {
+            if !self.in_trap {
+                self.run_trap(Condition::Err);
+            }
+            if self.options.errexit {
+                self.exit_shell(status);
+            }
+        }
+    }
+
+    // set -x, prints every expanded command of the pipeline to stderr prefixed with PS4
+    fn trace(&mut self, command_list: &CommandList) {
+        let ps4 = self
+            .expander
+            .variables
+            .get_scalar("PS4")
+            .unwrap_or_else(|| String::from(DEFAULT_PS4));
+        for command in &command_list.commands {
+            let assignments = command.assignments.iter().map(|assignment| match &assignment.value {
+                AssignmentValue::Scalar(word) => {
+                    format!("{}={}", assignment.name, util::quote(&self.expander.expand_word_unsplit(word)))
+                }
+                AssignmentValue::Array(elements) => {
+                    let elements: Vec<String> = self.expander.expand_words(elements).iter().map(|e| util::quote(e)).collect();
+                    format!("{}=({})", assignment.name, elements.join(" "))
+                }
+            });
+            let name = Some(&command.command).filter(|name| !name.is_empty());
+            let fields = name.into_iter().chain(&command.args).map(|field| util::quote(field));
+            let line: Vec<String> = assignments.chain(fields).collect();
+            let _ = writeln!(self.xtrace_output, "{}{}", ps4, line.join(" "));
         }
+        // unset variables in assignments were reported by the trace, not by the assignment
+        let _ = self.expander.take_unbound();
     }
 
     fn toggle_logger(&mut self) {
         self.logger
-            .print_debug(String::from("Fesh"), format!("toggle debug logging"));
+            .print_debug(String::from("Fesh"), String::from("toggle debug logging"));
         self.input_reader.logger.toggle_debug();
         self.input_parser.logger.toggle_debug();
+        self.input_parser.tokenizer.logger.toggle_debug();
+        self.expander.logger.toggle_debug();
+        self.command_hash.logger.toggle_debug();
         self.file_writer.logger.toggle_debug();
         self.logger.toggle_debug();
     }
 
-    // currently only first command can be a builtin
-    fn check_first_builtin(&mut self, command_list: &CommandList) -> bool {
-        self.logger
-            .print_debug(String::from("Fesh"), format!("checking for builtin"));
-        if let Some(first_command) = command_list.commands.first() {
-            if first_command.command_type == CommandType::Builtin {
-                return self.execute_buitin(first_command.clone());
-            }
-            return false;
-        }
-        return false;
-    }
-
-    fn execute_buitin(&mut self, command_input: command::Command) -> bool {
+    // returns the exit status of the builtin
+    fn execute_buitin(&mut self, command_input: &command::Command, io: &mut BuiltinIo) -> i32 {
         self.logger.print_debug(
             String::from("Fesh"),
             format!("executing builtin: {}", command_input.command),
         );
         match command_input.command.as_str() {
             "aliases" => {
-                self.config.aliases.iter().for_each(|(k, v)| {
-                    println!("{} -> {}", k, v);
-                });
-                return true;
+                for (k, v) in self.config.aliases.iter() {
+                    let _ = writeln!(io.stdout, "{} -> {}", k, v);
+                }
+                0
             }
             "cd" => {
                 if command_input.args.is_empty() {
-                    self.logger.print_error(format!("cd: no argument provided"));
-                    return false;
+                    self.logger.write_error(&mut io.stderr, String::from("cd: no argument provided"));
+                    return 1;
                 }
                 let path = path::Path::new(&command_input.args[0]);
                 if !path.is_dir() {
                     self.logger
-                        .print_error(format!("cd: {:?} is not a directory", path));
-                    return false;
+                        .write_error(&mut io.stderr, format!("cd: {:?} is not a directory", path));
+                    return 1;
                 }
                 if let Err(e) = std::env::set_current_dir(path) {
                     self.logger
-                        .print_error(format!("cd: failed to change directory: {e}"));
-                    return false;
+                        .write_error(&mut io.stderr, format!("cd: failed to change directory: {e}"));
+                    return 1;
                 }
                 self.logger.print_debug(
                     String::from("Fesh"),
                     format!("changed directory to: {}", path.display()),
                 );
-                return true;
-            }
-            "exit" => {
-                exit(0);
+                0
             }
+            "exit" => self.exit(&command_input.args, io),
             "+debug" => {
                 self.toggle_logger();
-                return true;
+                0
+            }
+            "set" => self.set(&command_input.args, io),
+            "declare" => self.declare(command_input, io),
+            "export" => self.export(command_input, io),
+            "exec" => self.exec(command_input, io),
+            "hash" => self.hash(&command_input.args, io),
+            "type" => match command_input.args.split_first() {
+                Some((flag, names)) if flag == "-t" => self.describe_all("type", names, Describe::TypeShort, false, io),
+                _ => self.describe_all("type", &command_input.args, Describe::Type, false, io),
+            },
+            "which" => self.which(&command_input.args, io),
+            "command" => self.command(&command_input.args, io),
+            "ulimit" => self.ulimit(&command_input.args, io),
+            "umask" => self.umask(&command_input.args, io),
+            "trap" => self.trap(&command_input.args, io),
+            "source" | "." => self.source(&command_input.command, &command_input.args, io),
+            "eval" => self.eval(&command_input.args, io),
+            "read" => self.read(&command_input.args, io),
+            "unset" => self.unset(&command_input.args, io),
+            _ => 1,
+        }
+    }
+
+    // set [-Ceux] [+Ceux] [-o|+o [name]] [--] [args...], the remaining args become the
+    // positional parameters, without args the variables are printed
+    fn set(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        if args.is_empty() {
+            return self.print_declarations(Vec::new(), io);
+        }
+
+        let mut args = args.iter().peekable();
+        let mut params = false;
+        while let Some(arg) = args.next_if(|arg| arg.starts_with(['-', '+'])) {
+            if arg == "--" || arg == "-" {
+                params = true;
+                break;
             }
-            "set" => {
-                if command_input.args.len() < 2 {
-                    self.logger.print_error(format!("usage: set <VAR> <VALUE>"));
-                    return false;
+            let enable = arg.starts_with('-');
+            for flag in arg[1..].chars() {
+                let name = match flag {
+                    'o' => match args.next() {
+                        Some(name) => name.as_str(),
+                        None => {
+                            self.print_options(enable, io);
+                            continue;
+                        }
+                    },
+                    _ => match options::Options::from_flag(flag) {
+                        Some(name) => name,
+                        None => {
+                            self.logger.write_error(&mut io.stderr, format!("set: {}{}: invalid option", &arg[..1], flag));
+                            self.logger
+                                .write_error(&mut io.stderr, String::from("usage: set [-Ceux] [-o name] [--] [arg...]"));
+                            return STATUS_USAGE;
+                        }
+                    },
+                };
+                if let Err(e) = self.set_option(name, enable) {
+                    self.logger.write_error(&mut io.stderr, format!("set: {e}"));
+                    return STATUS_USAGE;
                 }
-                let key = &command_input.args[0];
-                let value = command_input.args[1..].join(" ");
-                // fesh is single-threaded!
-                unsafe { env::set_var(key, &value) };
-                self.logger
-                    .print_debug(String::from("Fesh"), format!("set {}={}", key, value));
-                return true;
             }
-            _ => false,
         }
-    }
 
-    pub fn execute_command_list(&mut self, command_list: CommandList) {
-        self.logger.print_debug(
-            String::from("Fesh"),
-            format!("executing command list: {:?}", command_list),
-        );
-        let is_builtin = self.check_first_builtin(&command_list);
-        if is_builtin {
-            return;
+        let rest: Vec<String> = args.cloned().collect();
+        if params || !rest.is_empty() {
+            self.expander.variables.positional_params = rest;
         }
+        0
+    }
 
-        let mut prev_stdout: Option<Stdio> = None;
-        let mut children = Vec::new();
-        let mut skip_next = false;
+    fn set_option(&mut self, name: &str, value: bool) -> Result<(), String> {
+        self.options.set(name, value)?;
+        self.expander.nounset = self.options.nounset;
+        self.logger
+            .print_debug(String::from("Fesh"), format!("set option {}={}", name, value));
+        Ok(())
+    }
 
-        for (i, command) in command_list.commands.iter().enumerate() {
-            if skip_next {
-                skip_next = false;
+    // `set -o` prints a table, `set +o` the commands that restore the options
+    fn print_options(&self, table: bool, io: &mut BuiltinIo) {
+        for name in options::Options::NAMES {
+            let enabled = self.options.get(name).unwrap_or(false);
+            let _ = if table {
+                writeln!(io.stdout, "{:<15}\t{}", name, if enabled { "on" } else { "off" })
+            } else {
+                writeln!(io.stdout, "set {}o {}", if enabled { "-" } else { "+" }, name)
+            };
+        }
+    }
+
+    // export [-p] [name[=value]...], moves variables to the environment of child processes,
+    // a name without a value is exported once it is set, with -p or without names the
+    // exported variables are printed
+    fn export(&mut self, command_input: &command::Command, io: &mut BuiltinIo) -> i32 {
+        let mut print = command_input.words.len() == 1;
+        let mut status = 0;
+        for word in &command_input.words[1..] {
+            if let Some(assignment) = Assignment::parse(word) {
+                let result = match &assignment.value {
+                    AssignmentValue::Scalar(value) if assignment.subscript.is_none() && !assignment.append => {
+                        let value = self.expander.expand_word_unsplit(value);
+                        self.expander
+                            .take_unbound()
+                            .and_then(|_| self.expander.variables.export(&assignment.name, Some(value)))
+                    }
+                    _ => Err(format!("{}: only name=value can be exported", assignment.name)),
+                };
+                if let Err(e) = result {
+                    self.logger.write_error(&mut io.stderr, format!("export: {e}"));
+                    status = 1;
+                }
                 continue;
             }
 
-            let operator = command_list.operators.get(i);
+            for field in self.expander.expand_word(word) {
+                let result = match field.as_str() {
+                    "-p" => {
+                        print = true;
+                        Ok(())
+                    }
+                    option if option.starts_with('-') => {
+                        self.logger.write_error(&mut io.stderr, format!("export: {option}: invalid option"));
+                        self.logger
+                            .write_error(&mut io.stderr, String::from("usage: export [-p] [name[=value]...]"));
+                        return STATUS_USAGE;
+                    }
+                    name if !Tokenizer::is_name(name) => Err(format!("`{name}': not a valid identifier")),
+                    name => self.expander.variables.export(name, None),
+                };
+                if let Err(e) = result {
+                    self.logger.write_error(&mut io.stderr, format!("export: {e}"));
+                    status = 1;
+                }
+            }
+        }
 
-            let mut cmd = SysCommand::new(&command.command);
-            if !command.args.is_empty() {
-                cmd.args(&command.args);
+        if print {
+            let mut vars: Vec<(String, Option<String>)> = env::vars().map(|(name, value)| (name, Some(value))).collect();
+            vars.extend(self.expander.variables.pending_exports().map(|name| (name.clone(), None)));
+            vars.sort();
+            for (name, value) in vars {
+                let _ = match value {
+                    Some(value) => writeln!(io.stdout, "declare -x {}={}", name, Self::quote_value(&value)),
+                    None => writeln!(io.stdout, "declare -x {}", name),
+                };
             }
+        }
+        status
+    }
 
-            if let Some(stdin) = prev_stdout.take() {
-                cmd.stdin(stdin);
-            } else {
-                cmd.stdin(Stdio::inherit());
+    // exec [cmd [args...]], replaces fesh with cmd, without cmd the redirects of exec
+    // become the shell's own stdin, stdout and stderr for all later commands
+    fn exec(&mut self, command_input: &command::Command, io: &mut BuiltinIo) -> i32 {
+        let Some((name, args)) = command_input.args.split_first() else {
+            return match io.install() {
+                Ok(()) => 0,
+                Err(e) => {
+                    self.logger.write_error(&mut io.stderr, format!("exec: {e}"));
+                    1
+                }
+            };
+        };
+
+        let spawn = self.prepare_spawn(PathBuf::from(name), name, args, &command_input.assignments, [None, None, None]);
+        let mut spawn = match spawn {
+            Ok(spawn) => spawn,
+            Err(e) => {
+                self.logger.write_error(&mut io.stderr, format!("exec: {e}"));
+                return 1;
+            }
+        };
+        // the shell is only rewired once the command is sure to run
+        let error = match self.find_exec_program(name, &spawn.env) {
+            Ok(program) => {
+                spawn.program = program;
+                match io.install() {
+                    Ok(()) => {
+                        let _ = io::stdout().flush();
+                        // only returns if the command could not be executed
+                        spawn.command().exec()
+                    }
+                    Err(e) => e,
+                }
             }
+            Err(e) => e,
+        };
+        let status = self.spawn_error_status(name, &error, &mut io.stderr);
+        // a script can't continue without the command it handed over to
+        if !self.interactive {
+            self.exit_shell(status);
+        }
+        status
+    }
 
-            match operator {
-                Some(Operator::Pipe) => {
-                    self.logger
-                        .print_debug(String::from("Fesh"), format!("executing pipe"));
-                    cmd.stdout(Stdio::piped());
-                }
-                Some(Operator::RedirectOverwrite) => {
-                    let path = path::Path::new(&command_list.commands.get(i + 1).unwrap().command);
-                    self.logger.print_debug(
-                        String::from("Fesh"),
-                        format!("executing redirect overwrite to <{}>", path.display()),
-                    );
-                    cmd.stdout(Stdio::piped());
-                    let output = match cmd.output() {
-                        Ok(o) => o,
-                        Err(e) => {
-                            self.logger
-                                .print_error(format!("error while redirect: {e:?}"));
-                            return;
-                        }
-                    };
-                    let output_str = String::from_utf8_lossy(&output.stdout);
-                    if let Err(e) = self.file_writer.overwrite_file(path, &output_str) {
-                        self.logger
-                            .print_error(format!("error writing to file: {e:?}"));
-                        return;
+    // the executable `exec` replaces the shell with, `PATH=dir exec cmd` searches the given PATH
+    fn find_exec_program(&mut self, name: &str, env: &[(String, String)]) -> io::Result<PathBuf> {
+        let program = match env.iter().rfind(|(variable, _)| variable == "PATH") {
+            Some((_, path)) if !name.contains('/') => util::find_in(OsStr::new(path), name),
+            _ => self.find_program(name, &[]),
+        };
+        match program {
+            Some(program) if util::is_executable(&program) => Ok(program),
+            Some(program) if program.exists() => Err(io::ErrorKind::PermissionDenied.into()),
+            _ => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    // hash [-r] [-p path] [name...], remembers where commands are, lists the remembered
+    // commands without arguments
+    fn hash(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let mut args = args.iter().peekable();
+        let mut path: Option<PathBuf> = None;
+        let mut listed = true;
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-')) {
+            match arg.as_str() {
+                "-r" => {
+                    self.command_hash.clear();
+                    listed = false;
+                }
+                "-p" => match args.next() {
+                    Some(p) => path = Some(PathBuf::from(p)),
+                    None => {
+                        self.logger.write_error(&mut io.stderr, String::from("hash: -p: option requires an argument"));
+                        return STATUS_USAGE;
                     }
+                },
+                "--" => break,
+                option => {
+                    self.logger.write_error(&mut io.stderr, format!("hash: {option}: invalid option"));
+                    self.logger
+                        .write_error(&mut io.stderr, String::from("usage: hash [-r] [-p path] [name...]"));
+                    return STATUS_USAGE;
+                }
+            }
+        }
 
-                    skip_next = true;
-                    continue;
+        let names: Vec<&String> = args.collect();
+        if names.is_empty() {
+            if path.is_some() {
+                self.logger.write_error(&mut io.stderr, String::from("usage: hash -p path name"));
+                return STATUS_USAGE;
+            }
+            if listed {
+                self.print_hash(io);
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for name in names {
+            if let Some(path) = &path {
+                self.command_hash.add(name, path.clone());
+            } else if command::Command::is_builtin(name) {
+                // builtins are never looked up on PATH
+            } else if !self.command_hash.remember(name) {
+                self.logger.write_error(&mut io.stderr, format!("hash: {name}: not found"));
+                status = 1;
+            }
+        }
+        status
+    }
+
+    fn print_hash(&mut self, io: &mut BuiltinIo) {
+        let entries = self.command_hash.entries();
+        if entries.is_empty() {
+            let _ = writeln!(io.stdout, "hash: hash table empty");
+            return;
+        }
+        let _ = writeln!(io.stdout, "hits\tcommand");
+        for (_, entry) in entries {
+            let _ = writeln!(io.stdout, "{:>4}\t{}", entry.hits, entry.path.display());
+        }
+    }
+
+    // command -v|-V name..., `command name args` never gets here, the executor runs name
+    // without looking up aliases
+    fn command(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        match args.split_first() {
+            Some((flag, names)) if flag == "-v" => self.describe_all("command", names, Describe::Command, false, io),
+            Some((flag, names)) if flag == "-V" => self.describe_all("command", names, Describe::Type, false, io),
+            None => 0,
+            Some((option, _)) => {
+                self.logger.write_error(&mut io.stderr, format!("command: {option}: invalid option"));
+                self.logger
+                    .write_error(&mut io.stderr, String::from("usage: command [-v|-V] name [args...]"));
+                STATUS_USAGE
+            }
+        }
+    }
+
+    // which [-a] name..., -a lists everything a name could run and not only the first
+    fn which(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        match args.split_first() {
+            Some((flag, names)) if flag == "-a" => self.describe_all("which", names, Describe::Which, true, io),
+            Some((flag, names)) if flag == "--" => self.describe_all("which", names, Describe::Which, false, io),
+            Some((option, _)) if option.starts_with('-') && option.len() > 1 => {
+                self.logger.write_error(&mut io.stderr, format!("which: {option}: invalid option"));
+                self.logger.write_error(&mut io.stderr, String::from("usage: which [-a] name..."));
+                STATUS_USAGE
+            }
+            _ => self.describe_all("which", args, Describe::Which, false, io),
+        }
+    }
+
+    // prints what the names resolve to, with `all` every resolution in order, fails if any
+    // of them is not found
+    fn describe_all(&mut self, builtin: &str, names: &[String], style: Describe, all: bool, io: &mut BuiltinIo) -> i32 {
+        let mut status = 0;
+        for name in names {
+            let mut resolutions = self.resolve_all(name);
+            if !all {
+                resolutions.truncate(1);
+            }
+            if resolutions.is_empty() {
+                // `type -t` and `command -v` fail silently like in bash
+                if matches!(style, Describe::Type | Describe::Which) {
+                    self.logger.write_error(&mut io.stderr, format!("{builtin}: {name}: not found"));
                 }
-                Some(Operator::RedirectAppend) => {
-                    let path = path::Path::new(&command_list.commands.get(i + 1).unwrap().command);
-                    self.logger.print_debug(
-                        String::from("Fesh"),
-                        format!("executing redirect append to <{}>", path.display()),
-                    );
-                    cmd.stdout(Stdio::piped());
-                    let output = match cmd.output() {
-                        Ok(o) => o,
-                        Err(e) => {
-                            self.logger
-                                .print_error(format!("error while redirect append: {e:?}"));
-                            return;
+                status = 1;
+            }
+            for resolution in resolutions {
+                let _ = writeln!(io.stdout, "{}", Self::describe(name, resolution, style));
+            }
+        }
+        status
+    }
+
+    fn describe(name: &str, resolution: Resolution, style: Describe) -> String {
+        match (resolution, style) {
+            (resolution, Describe::TypeShort) => String::from(match resolution {
+                Resolution::Keyword => "keyword",
+                Resolution::Alias(_) => "alias",
+                Resolution::Builtin => "builtin",
+                Resolution::File { .. } => "file",
+            }),
+            (Resolution::Keyword, Describe::Type) => format!("{name} is a shell keyword"),
+            (Resolution::Keyword, Describe::Which) => format!("{name}: shell reserved word"),
+            (Resolution::Alias(value), Describe::Type) => format!("{name} is aliased to `{value}'"),
+            (Resolution::Alias(value), Describe::Command) => format!("alias {name}={}", util::quote(&value)),
+            (Resolution::Alias(value), Describe::Which) => format!("{name}: aliased to {value}"),
+            (Resolution::Builtin, Describe::Type) => format!("{name} is a shell builtin"),
+            (Resolution::Builtin, Describe::Which) => format!("{name}: shell built-in command"),
+            (Resolution::Keyword | Resolution::Builtin, Describe::Command) => name.to_string(),
+            (Resolution::File { path, hashed: true }, Describe::Type) => format!("{name} is hashed ({})", path.display()),
+            (Resolution::File { path, .. }, Describe::Type) => format!("{name} is {}", path.display()),
+            (Resolution::File { path, .. }, _) => path.display().to_string(),
+        }
+    }
+
+    // everything a command name resolves to, in the order the executor tries them: keywords
+    // are handled by the parser, then aliases are replaced, then builtins run and other
+    // commands are searched in the hash table and on PATH
+    fn resolve_all(&mut self, name: &str) -> Vec<Resolution> {
+        let mut resolutions = Vec::new();
+        if command::KEYWORDS.contains(&name) {
+            resolutions.push(Resolution::Keyword);
+        }
+        if let Some(value) = self.config.aliases.get(name) {
+            resolutions.push(Resolution::Alias(value.clone()));
+        }
+        if command::Command::is_builtin(name) {
+            resolutions.push(Resolution::Builtin);
+        }
+        let files = self.command_hash.find_all(name).into_iter();
+        resolutions.extend(files.map(|(path, hashed)| Resolution::File { path, hashed }));
+        resolutions
+    }
+
+    // ulimit [-SHa] [-c|-d|-e|-f|-i|-l|-m|-n|-q|-r|-s|-t|-u|-v|-x [limit]]..., shows or sets
+    // the resource limits of the shell, which child processes inherit, -f is the default
+    fn ulimit(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let mut kind = limits::Kind::Both;
+        let mut all = false;
+        let mut requests: Vec<(&Limit, Option<&String>)> = Vec::new();
+        let mut args = args.iter().peekable();
+
+        while let Some(arg) = args.next() {
+            let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+                requests.push((limits::DEFAULT, Some(arg)));
+                continue;
+            };
+            for flag in flags.chars() {
+                match flag {
+                    'S' => kind = limits::Kind::Soft,
+                    'H' => kind = limits::Kind::Hard,
+                    'a' => all = true,
+                    _ => match Limit::from_flag(flag) {
+                        Some(limit) => requests.push((limit, None)),
+                        None => {
+                            self.logger.write_error(&mut io.stderr, format!("ulimit: -{flag}: invalid option"));
+                            self.logger.write_error(
+                                &mut io.stderr,
+                                String::from("usage: ulimit [-SHa] [-cdefilmnqrstuvx [limit]]"),
+                            );
+                            return STATUS_USAGE;
                         }
+                    },
+                }
+            }
+            // the value belongs to the last limit of the flags, like `-n 1024`
+            if let Some(request) = requests.last_mut().filter(|_| flags.ends_with(|c| Limit::from_flag(c).is_some())) {
+                request.1 = args.next_if(|value| !value.starts_with('-'));
+            }
+        }
+
+        if all {
+            requests = limits::LIMITS.iter().map(|limit| (limit, None)).collect();
+        } else if requests.is_empty() {
+            requests.push((limits::DEFAULT, None));
+        }
+
+        let mut status = 0;
+        let described = requests.len() > 1;
+        for (limit, value) in requests {
+            let result = match value {
+                Some(value) => limit.set(kind, value),
+                None => limit.get(kind).map_err(|e| e.to_string()).map(|value| {
+                    let _ = match described {
+                        true => writeln!(io.stdout, "{}", limit.describe(&value)),
+                        false => writeln!(io.stdout, "{}", value),
                     };
-                    let output_str = String::from_utf8_lossy(&output.stdout);
-                    if let Err(e) = self.file_writer.append_to_file(path, &output_str) {
-                        self.logger
-                            .print_error(format!("error appending to file: {e:?}"));
-                        return;
+                }),
+            };
+            if let Err(e) = result {
+                self.logger.write_error(&mut io.stderr, format!("ulimit: {e}"));
+                status = 1;
+            }
+        }
+        status
+    }
+
+    // umask [-S] [mode], shows or sets the permissions new files don't get, in octal or
+    // with -S as the permissions they may get
+    fn umask(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let (symbolic, args) = match args.split_first() {
+            Some((flag, rest)) if flag == "-S" => (true, rest),
+            _ => (false, args),
+        };
+        // umask can only be read by setting it
+        let current = stat::umask(Mode::empty());
+        stat::umask(current);
+        let current = current.bits() as u32;
+
+        match args {
+            [] if symbolic => {
+                let _ = writeln!(io.stdout, "{}", mode::symbolic(current));
+            }
+            [] => {
+                let _ = writeln!(io.stdout, "{:04o}", current);
+            }
+            [text] => match mode::parse_umask(text, current) {
+                Ok(mask) => {
+                    stat::umask(Mode::from_bits_truncate(mask as libc::mode_t));
+                    if symbolic {
+                        let _ = writeln!(io.stdout, "{}", mode::symbolic(mask));
                     }
+                }
+                Err(e) => {
+                    self.logger.write_error(&mut io.stderr, format!("umask: {e}"));
+                    return 1;
+                }
+            },
+            _ => {
+                self.logger.write_error(&mut io.stderr, String::from("usage: umask [-S] [mode]"));
+                return STATUS_USAGE;
+            }
+        }
+        0
+    }
 
-                    skip_next = true;
-                    continue;
+    // exit [n], exits with the status of the last command if n is not given
+    fn exit(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let status = match args.first() {
+            Some(arg) => match arg.parse::<i32>() {
+                Ok(status) => status & 0xff,
+                Err(_) => {
+                    self.logger.write_error(&mut io.stderr, format!("exit: {arg}: numeric argument required"));
+                    STATUS_USAGE
                 }
-                None => {
-                    cmd.stdout(Stdio::inherit());
+            },
+            None => self.last_status(),
+        };
+        io.flush();
+        self.exit_shell(status);
+        status
+    }
+
+    // trap [-p] [action condition...], runs the action when a signal arrives or for the
+    // EXIT, ERR and DEBUG pseudo-signals, `-` resets and an empty action ignores the signal,
+    // without an action the traps are listed
+    fn trap(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let (print, args) = match args.split_first() {
+            Some((flag, rest)) if flag == "-p" => (true, rest),
+            _ => (false, args),
+        };
+        let args = args.strip_prefix(&[String::from("--")]).unwrap_or(args);
+        let mut status = 0;
+
+        if print || args.is_empty() {
+            let mut conditions = Vec::new();
+            for arg in args {
+                match Condition::parse(arg) {
+                    Ok(condition) => conditions.push(condition),
+                    Err(e) => {
+                        self.logger.write_error(&mut io.stderr, format!("trap: {e}"));
+                        status = 1;
+                    }
                 }
             }
+            for command in self.traps.commands(&conditions) {
+                let _ = writeln!(io.stdout, "{command}");
+            }
+            return status;
+        }
 
-            let mut child = match cmd.spawn() {
-                Ok(c) => c,
+        // a lone condition is reset like `trap - SIG`
+        let (action, names) = match args.split_first() {
+            Some((name, [])) => ("-", std::slice::from_ref(name)),
+            Some((action, names)) => (action.as_str(), names),
+            None => return 0,
+        };
+        for name in names {
+            let condition = match Condition::parse(name) {
+                Ok(condition) => condition,
                 Err(e) => {
-                    self.logger.print_error(format!(
-                        "failed to spawn child process <{}>: {e}",
-                        command.command
-                    ));
-                    return;
+                    self.logger.write_error(&mut io.stderr, format!("trap: {e}"));
+                    status = 1;
+                    continue;
                 }
             };
-
-            if operator == Some(&Operator::Pipe) {
-                if let Some(stdout) = child.stdout.take() {
-                    prev_stdout = Some(Stdio::from(stdout));
+            match action {
+                "-" => self.traps.reset(condition),
+                _ => self.traps.set(condition, action.to_string()),
+            }
+            if let Condition::Signal(sig) = condition {
+                match action {
+                    "-" => signals::restore(sig),
+                    "" => signals::ignore(sig),
+                    _ => signals::catch(sig),
                 }
             }
-
-            children.push(child);
         }
+        status
+    }
 
-        for mut child in children {
-            if let Err(e) = child.wait() {
+    // source file [args...], runs the commands of a file in the shell itself, so `cd` and
+    // variables persist, the args are the positional parameters while it runs
+    fn source(&mut self, builtin: &str, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let Some((file, params)) = args.split_first() else {
+            self.logger
+                .write_error(&mut io.stderr, format!("usage: {builtin} file [args...]"));
+            return STATUS_USAGE;
+        };
+        if self.source_depth >= MAX_SOURCE_DEPTH {
+            self.logger
+                .write_error(&mut io.stderr, format!("{builtin}: {file}: maximum nesting depth exceeded"));
+            return 1;
+        }
+        let input = match std::fs::read_to_string(file) {
+            Ok(input) => input,
+            Err(e) => {
+                self.logger.write_error(&mut io.stderr, format!("{builtin}: {file}: {e}"));
+                return 1;
+            }
+        };
+        let command_lists = match self.input_parser.parse_script_numbered(&input) {
+            Ok(command_lists) => command_lists,
+            Err((line, e)) => {
                 self.logger
-                    .print_error(format!("failed to wait for child process: {e}"));
+                    .write_error(&mut io.stderr, format!("{file}:{line}: syntax error: {e}"));
+                return STATUS_USAGE;
+            }
+        };
+        // the first command of the file still sees `$?` from before, an empty file succeeds
+        if command_lists.is_empty() {
+            return 0;
+        }
+        io.flush();
+
+        // without args the file sees the positional parameters of the shell
+        let saved_params = match params.is_empty() {
+            true => None,
+            false => Some(std::mem::replace(
+                &mut self.expander.variables.positional_params,
+                params.to_vec(),
+            )),
+        };
+        let saved_location = self.logger.location.take();
+        self.source_depth += 1;
+        for (line, command_list) in command_lists {
+            self.logger.location = Some(format!("{file}:{line}"));
+            self.execute_line(command_list);
+            if self.exiting.is_some() {
+                break;
+            }
+            self.run_pending_traps();
+        }
+        self.source_depth -= 1;
+        self.logger.location = saved_location;
+        if let Some(params) = saved_params {
+            self.expander.variables.positional_params = params;
+        }
+        self.last_status()
+    }
+
+    // eval [args...], joins the args with spaces and runs them as commands in the shell
+    // itself, returns the status of the last command
+    fn eval(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let command_lists = match self.input_parser.parse_script(&args.join(" ")) {
+            Ok(command_lists) => command_lists,
+            Err(e) => {
+                self.logger.write_error(&mut io.stderr, format!("eval: syntax error: {e}"));
+                return STATUS_USAGE;
+            }
+        };
+        // like for source, the commands still see `$?` from before
+        if command_lists.is_empty() {
+            return 0;
+        }
+        io.flush();
+
+        for command_list in command_lists {
+            self.execute_line(command_list);
+            if self.exiting.is_some() {
+                break;
+            }
+        }
+        self.last_status()
+    }
+
+    // unset [-v] name..., removes shell and exported variables, `unset 'arr[k]'` removes a
+    // single element and `unset 'arr[@]'` the whole array
+    fn unset(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let mut names = args;
+        while let Some((flag, rest)) = names.split_first().filter(|(flag, _)| flag.starts_with('-')) {
+            names = rest;
+            match flag.as_str() {
+                "-v" => {}
+                "--" => break,
+                _ => {
+                    self.logger.write_error(&mut io.stderr, format!("unset: {flag}: invalid option"));
+                    self.logger.write_error(&mut io.stderr, String::from("usage: unset [-v] [name...]"));
+                    return STATUS_USAGE;
+                }
+            }
+        }
+
+        let mut status = 0;
+        for name in names {
+            let result = match name.strip_suffix(']').and_then(|name| name.split_once('[')) {
+                Some((array, "@" | "*")) if Tokenizer::is_name(array) => {
+                    self.expander.variables.unset(array);
+                    Ok(())
+                }
+                Some((array, subscript)) if Tokenizer::is_name(array) => {
+                    let key = self.expander.expand_subscript(array, subscript);
+                    self.expander.variables.unset_element(array, &key)
+                }
+                _ if Tokenizer::is_name(name) => {
+                    self.expander.variables.unset(name);
+                    Ok(())
+                }
+                _ => Err(format!("`{name}': not a valid identifier")),
+            };
+            if let Err(e) = result {
+                self.logger.write_error(&mut io.stderr, format!("unset: {e}"));
+                status = 1;
             }
         }
+        status
+    }
+
+    // read [-rs] [-p prompt] [-t timeout] [-n count] [-d delim] [-a array] [name...], reads a
+    // line from stdin and splits it with IFS into the names, the last name gets the rest of
+    // the line, without names the whole line goes to REPLY
+    fn read(&mut self, args: &[String], io: &mut BuiltinIo) -> i32 {
+        let mut options = ReadOptions {
+            raw: false,
+            delimiter: b'\n',
+            count: None,
+            timeout: None,
+        };
+        let mut silent = false;
+        let mut prompt: Option<String> = None;
+        let mut array: Option<String> = None;
+
+        let usage = "usage: read [-rs] [-p prompt] [-t timeout] [-n count] [-d delim] [-a array] [name...]";
+        let mut next = 0;
+        while let Some(arg) = args.get(next) {
+            if arg == "--" {
+                next += 1;
+                break;
+            }
+            let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+                break;
+            };
+            next += 1;
+            for (i, flag) in flags.char_indices() {
+                match flag {
+                    'r' => options.raw = true,
+                    's' => silent = true,
+                    'p' | 't' | 'n' | 'd' | 'a' => {
+                        // the value is the rest of the arg or the next arg, like `-p` `prompt`
+                        let attached = &flags[i + 1..];
+                        let value = match (attached.is_empty(), args.get(next)) {
+                            (false, _) => attached.to_string(),
+                            (true, Some(value)) => {
+                                next += 1;
+                                value.clone()
+                            }
+                            (true, None) => {
+                                self.logger
+                                    .write_error(&mut io.stderr, format!("read: -{flag}: option requires an argument"));
+                                self.logger.write_error(&mut io.stderr, usage.to_string());
+                                return STATUS_USAGE;
+                            }
+                        };
+                        match flag {
+                            'p' => prompt = Some(value),
+                            't' => match value.parse::<f64>().ok().and_then(|secs| Duration::try_from_secs_f64(secs).ok()) {
+                                Some(timeout) => options.timeout = Some(timeout),
+                                None => {
+                                    self.logger
+                                        .write_error(&mut io.stderr, format!("read: {value}: invalid timeout specification"));
+                                    return 1;
+                                }
+                            },
+                            'n' => match value.parse::<usize>() {
+                                Ok(count) => options.count = Some(count),
+                                Err(_) => {
+                                    self.logger.write_error(&mut io.stderr, format!("read: {value}: invalid number"));
+                                    return 1;
+                                }
+                            },
+                            // `-d ''` reads up to a NUL byte
+                            'd' => options.delimiter = value.bytes().next().unwrap_or(0),
+                            _ => array = Some(value),
+                        }
+                        break;
+                    }
+                    _ => {
+                        self.logger.write_error(&mut io.stderr, format!("read: -{flag}: invalid option"));
+                        self.logger.write_error(&mut io.stderr, usage.to_string());
+                        return STATUS_USAGE;
+                    }
+                }
+            }
+        }
+
+        let names = &args[next..];
+        if let Some(name) = array.iter().chain(names).find(|name| !Tokenizer::is_name(name)) {
+            self.logger
+                .write_error(&mut io.stderr, format!("read: `{name}': not a valid identifier"));
+            return 1;
+        }
+
+        let is_terminal = unsafe { libc::isatty(io.stdin.as_fd().as_raw_fd()) } == 1;
+        // `-t 0` only tells whether input is waiting
+        if options.timeout == Some(Duration::ZERO) {
+            return match read_line::wait_readable(io.stdin.as_fd(), Duration::ZERO) {
+                Ok(true) => 0,
+                _ => 1,
+            };
+        }
+        if let Some(prompt) = prompt.filter(|_| is_terminal) {
+            let _ = write!(io.stderr, "{prompt}");
+            let _ = io.stderr.flush();
+        }
+
+        let echo_off = silent.then(|| read_line::EchoOff::new(io.stdin.as_fd()));
+        let result = read_line::read_line(&mut io.stdin, &options);
+        drop(echo_off);
+        let (line, end) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                self.logger.write_error(&mut io.stderr, format!("read: {e}"));
+                return 1;
+            }
+        };
+
+        let ifs = self
+            .expander
+            .variables
+            .get_scalar("IFS")
+            .unwrap_or_else(|| String::from(expander::DEFAULT_IFS));
+        match (&array, names) {
+            (Some(name), _) => {
+                let fields = read_line::split_fields(&line, &ifs, None);
+                self.expander.variables.set(name, Value::Indexed(fields.into_iter().enumerate().collect()));
+            }
+            // REPLY gets the line as it was read
+            (None, []) => {
+                let reply: String = line.iter().map(|(c, _)| *c).collect();
+                self.expander.variables.set_scalar("REPLY", reply);
+            }
+            (None, names) => {
+                let mut fields = read_line::split_fields(&line, &ifs, Some(names.len())).into_iter();
+                for name in names {
+                    self.expander.variables.set_scalar(name, fields.next().unwrap_or_default());
+                }
+            }
+        }
+
+        match end {
+            End::Delimiter | End::Count => 0,
+            End::Eof => 1,
+            End::Timeout => STATUS_SIGNAL_BASE + Signal::SIGALRM as i32,
+        }
+    }
+
+    // declare [-a|-A|-p] [name[=value]...]
+    fn declare(&mut self, command_input: &command::Command, io: &mut BuiltinIo) -> i32 {
+        let mut indexed = false;
+        let mut associative = false;
+        let mut print = false;
+        let mut names: Vec<String> = Vec::new();
+        let mut status = 0;
+
+        for word in &command_input.words[1..] {
+            if let Some(assignment) = Assignment::parse(word) {
+                if let Err(e) = self.declare_type(&assignment.name, indexed, associative) {
+                    self.logger.write_error(&mut io.stderr, format!("declare: {e}"));
+                    status = 1;
+                    continue;
+                }
+                // `declare -a arr=x` assigns element 0
+                let assignment = match (&assignment.value, indexed || associative) {
+                    (AssignmentValue::Scalar(_), true) if assignment.subscript.is_none() => Assignment {
+                        subscript: Some(Word::literal("0")),
+                        ..assignment
+                    },
+                    _ => assignment,
+                };
+                if let Err(e) = self.expander.apply_assignment(&assignment) {
+                    self.logger.write_error(&mut io.stderr, format!("declare: {e}"));
+                    status = 1;
+                }
+                continue;
+            }
+
+            for field in self.expander.expand_word(word) {
+                match field.as_str() {
+                    "-a" => indexed = true,
+                    "-A" => associative = true,
+                    "-p" => print = true,
+                    option if option.starts_with('-') => {
+                        self.logger.write_error(&mut io.stderr, format!("declare: {option}: invalid option"));
+                        self.logger
+                            .write_error(&mut io.stderr, String::from("usage: declare [-a|-A|-p] [name[=value]...]"));
+                        return STATUS_USAGE;
+                    }
+                    name => {
+                        if print {
+                            names.push(name.to_string());
+                        } else if let Err(e) = self.declare_type(name, indexed, associative) {
+                            self.logger.write_error(&mut io.stderr, format!("declare: {e}"));
+                            status = 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        if (print || command_input.words.len() == 1) && self.print_declarations(names, io) != 0 {
+            status = 1;
+        }
+
+        status
+    }
+
+    // prints the named variables, or all shell variables if no names are given
+    fn print_declarations(&self, mut names: Vec<String>, io: &mut BuiltinIo) -> i32 {
+        let mut status = 0;
+        if names.is_empty() {
+            names = self.expander.variables.names().into_iter().cloned().collect();
+        }
+        for name in names {
+            match self.expander.variables.get(&name) {
+                Some(value) => {
+                    let _ = writeln!(io.stdout, "{}", Self::format_declaration(&name, value));
+                }
+                None => match self.expander.variables.get_scalar(&name) {
+                    Some(value) => {
+                        let _ = writeln!(io.stdout, "declare -x {}={}", name, Self::quote_value(&value));
+                    }
+                    None => {
+                        self.logger.write_error(&mut io.stderr, format!("declare: {name}: not found"));
+                        status = 1;
+                    }
+                },
+            }
+        }
+        status
+    }
+
+    // arrays keep their elements, so they can't be turned into the other kind of array
+    fn declare_type(&mut self, name: &str, indexed: bool, associative: bool) -> Result<(), String> {
+        let variables = &mut self.expander.variables;
+        match variables.get(name) {
+            Some(Value::Indexed(_)) if associative => {
+                return Err(format!("{name}: cannot convert indexed to associative array"));
+            }
+            Some(Value::Associative(_)) if indexed => {
+                return Err(format!("{name}: cannot convert associative to indexed array"));
+            }
+            Some(Value::Indexed(_) | Value::Associative(_)) => {}
+            _ if associative => variables.set(name, Value::Associative(Default::default())),
+            _ if indexed => {
+                let current = variables.get_scalar(name);
+                variables.set(name, Value::Indexed(current.into_iter().enumerate().collect()));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn format_declaration(name: &str, value: &Value) -> String {
+        match value {
+            Value::Scalar(s) => format!("declare -- {}={}", name, Self::quote_value(s)),
+            Value::Indexed(map) => {
+                let elements: Vec<String> = map
+                    .iter()
+                    .map(|(k, v)| format!("[{}]={}", k, Self::quote_value(v)))
+                    .collect();
+                format!("declare -a {}=({})", name, elements.join(" "))
+            }
+            Value::Associative(map) => {
+                let elements: Vec<String> = map
+                    .iter()
+                    .map(|(k, v)| format!("[{}]={}", k, Self::quote_value(v)))
+                    .collect();
+                format!("declare -A {}=({})", name, elements.join(" "))
+            }
+        }
+    }
+
+    fn quote_value(value: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in value.chars() {
+            if matches!(c, '"' | '\\' | '$' | '`') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    // returns the exit status of the command list, the status of the last command of a pipeline,
+    // or with pipefail of the last command that failed, the statuses of all commands are
+    // stored in PIPESTATUS
+    pub fn execute_command_list(&mut self, command_list: CommandList) -> i32 {
+        self.logger.print_debug(
+            String::from("Fesh"),
+            format!("executing command list: {:?}", command_list),
+        );
+        let started = command_list.time.map(|_| (Instant::now(), Usage::of_shell()));
+        let mut children = Usage::default();
+        let statuses = self.execute_pipeline(&command_list, &mut children);
+        self.logger
+            .print_debug(String::from("Fesh"), format!("pipeline statuses: {:?}", statuses));
+
+        if let (Some(time), Some((start, shell))) = (command_list.time, started) {
+            self.print_times(time, start.elapsed(), shell, children);
+        }
+
+        let status = Self::pipeline_status(&statuses, self.options.pipefail, command_list.negated);
+        let pipestatus = statuses.into_iter().map(|s| s.to_string()).enumerate().collect();
+        self.expander.variables.set("PIPESTATUS", Value::Indexed(pipestatus));
+        status
+    }
+
+    fn pipeline_status(statuses: &[i32], pipefail: bool, negated: bool) -> i32 {
+        let status = if pipefail {
+            statuses.iter().rev().find(|s| **s != 0).copied().unwrap_or(0)
+        } else {
+            statuses.last().copied().unwrap_or(0)
+        };
+        if negated {
+            return (status == 0) as i32;
+        }
+        status
+    }
+
+    // prints the times of a pipeline run with `time` to stderr, the cpu times are the ones of
+    // the commands and of the shell, e.g. for builtins
+    fn print_times(&self, time: Time, real: Duration, shell_before: io::Result<Usage>, children: Usage) {
+        let mut usage = children;
+        if let (Ok(before), Ok(after)) = (shell_before, Usage::of_shell()) {
+            let shell = after.since(before);
+            usage.user += shell.user;
+            usage.sys += shell.sys;
+        }
+        let format = match time {
+            Time::Posix => String::from(timing::POSIX_FORMAT),
+            Time::Default => self
+                .expander
+                .variables
+                .get_scalar("TIMEFORMAT")
+                .unwrap_or_else(|| String::from(timing::DEFAULT_FORMAT)),
+        };
+        // an empty TIMEFORMAT turns the output off
+        if !format.is_empty() {
+            eprintln!("{}", timing::format(&format, real, &usage));
+        }
+    }
+
+    // runs the commands of a pipeline, returns the exit status of every command and adds
+    // the resources used by the child processes to usage
+    fn execute_pipeline(&mut self, command_list: &CommandList, usage: &mut Usage) -> Vec<i32> {
+        if command_list.commands.is_empty() {
+            return Vec::new();
+        }
+        // a command of only assignments sets shell variables, its redirects only create files
+        if let [command] = command_list.commands.as_slice()
+            && command.words.is_empty()
+        {
+            if let Err(e) = self.open_redirects(&command.redirects, &mut [None, None, None]) {
+                self.logger.print_error(e);
+                return vec![1];
+            }
+            for assignment in &command.assignments {
+                if let Err(e) = self.expander.apply_assignment(assignment) {
+                    self.logger.print_error(e);
+                    return vec![1];
+                }
+            }
+            return vec![0];
+        }
+
+        // a builtin on its own runs in the shell, so `cd` and `declare` affect it
+        if let [command] = command_list.commands.as_slice()
+            && command.command_type == CommandType::Builtin
+        {
+            let mut fds = [None, None, None];
+            if let Err(e) = self.open_redirects(&command.redirects, &mut fds) {
+                self.logger.print_error(e);
+                return vec![1];
+            }
+            let mut io = BuiltinIo::new(fds);
+            // `name=value builtin` only sets the variables while the builtin runs
+            let saved: Vec<(&str, Saved)> = command
+                .assignments
+                .iter()
+                .map(|a| (a.name.as_str(), self.expander.variables.save(&a.name)))
+                .collect();
+            let status = match self.apply_assignments(&command.assignments) {
+                Ok(()) => self.execute_buitin(command, &mut io),
+                Err(e) => {
+                    self.logger.write_error(&mut io.stderr, e);
+                    1
+                }
+            };
+            io.flush();
+            for (name, saved) in saved.into_iter().rev() {
+                self.expander.variables.restore(name, saved);
+            }
+            return vec![status];
+        }
+
+        let mut prev_stdout: Option<OwnedFd> = None;
+        let mut children = Vec::new();
+        let mut statuses = vec![0; command_list.commands.len()];
+
+        for (i, command) in command_list.commands.iter().enumerate() {
+            // stdin, stdout and stderr of the stage, None inherits the one of the shell
+            let mut fds: [Option<OwnedFd>; 3] = [prev_stdout.take(), None, None];
+
+            if command_list.operators.get(i) == Some(&Operator::Pipe) {
+                self.logger
+                    .print_debug(String::from("Fesh"), String::from("executing pipe"));
+                match io::pipe() {
+                    Ok((reader, writer)) => {
+                        fds[1] = Some(writer.into());
+                        prev_stdout = Some(reader.into());
+                    }
+                    Err(e) => {
+                        self.logger.print_error(format!("failed to create pipe: {e}"));
+                        statuses[i] = 1;
+                        statuses.truncate(i + 1);
+                        break;
+                    }
+                }
+            }
+
+            // redirects are applied after the pipe, so they take precedence over it,
+            // the command is not run if a redirect fails
+            if let Err(e) = self.open_redirects(&command.redirects, &mut fds) {
+                self.logger.print_error(e);
+                statuses[i] = 1;
+                continue;
+            }
+            // `$EMPTY` expands to no command at all
+            if command.words.is_empty() || (command.command.is_empty() && command.args.is_empty()) {
+                continue;
+            }
+
+            // builtins in a pipeline run in a forked child like every other stage
+            if command.command_type == CommandType::Builtin {
+                match self.fork_builtin(command, fds) {
+                    Ok(pid) => {
+                        signals::add_child(pid.as_raw() as u32);
+                        children.push((i, Stage::Forked(pid)));
+                    }
+                    Err(e) => {
+                        self.logger.print_error(format!("failed to fork: {e}"));
+                        statuses[i] = 1;
+                    }
+                }
+                continue;
+            }
+
+            // errors of the stage go to its stderr, a redirected one is kept open past the spawn
+            let mut stderr = match &fds[2] {
+                Some(fd) => fd.try_clone().map(|fd| Output::File(File::from(fd))).unwrap_or(Output::Stderr),
+                None => Output::Stderr,
+            };
+
+            // a configured handler runs in place of a missing command, with the same pipes
+            let program = self.find_program(&command.command, &command.assignments);
+            let result = match (program, &self.config.command_not_found.handler) {
+                (Some(program), _) => {
+                    self.prepare_spawn(program, &command.command, &command.args, &command.assignments, fds)
+                }
+                (None, Some(handler)) => {
+                    let args: Vec<String> = std::iter::once(&command.command).chain(&command.args).cloned().collect();
+                    self.prepare_spawn(handler.clone(), &handler.to_string_lossy(), &args, &[], fds)
+                }
+                (None, None) => {
+                    self.command_not_found(&command.command, &mut stderr);
+                    statuses[i] = STATUS_NOT_FOUND;
+                    continue;
+                }
+            };
+            let spawn = match result {
+                Ok(spawn) => spawn,
+                Err(e) => {
+                    self.logger.print_error(e);
+                    statuses[i] = 1;
+                    continue;
+                }
+            };
+
+            // the fds of the spawn are dropped after spawning, which closes the pipe ends of fesh
+            match self.executor.spawn(spawn) {
+                Ok(pid) => children.push((i, Stage::Process(pid))),
+                Err(e) => statuses[i] = self.spawn_error_status(&command.command, &e, &mut stderr),
+            }
+        }
+
+        for (i, stage) in children {
+            statuses[i] = match self.wait(stage) {
+                Ok((exit_status, stage_usage)) => {
+                    usage.add(stage_usage);
+                    exit_status
+                }
+                Err(e) => {
+                    self.logger
+                        .print_error(format!("failed to wait for child process: {e}"));
+                    1
+                }
+            };
+        }
+        statuses
+    }
+
+    // what to spawn for an external command, `name=value cmd` assignments only set the
+    // variable in the environment of cmd
+    fn prepare_spawn(
+        &mut self,
+        program: PathBuf,
+        name: &str,
+        args: &[String],
+        assignments: &[Assignment],
+        fds: [Option<OwnedFd>; 3],
+    ) -> Result<Spawn, String> {
+        let mut env = Vec::new();
+        for assignment in assignments {
+            if let AssignmentValue::Scalar(word) = &assignment.value {
+                env.push((assignment.name.clone(), self.expander.expand_word_unsplit(word)));
+            }
+        }
+        self.expander.take_unbound()?;
+        Ok(Spawn {
+            program,
+            arg0: name.to_string(),
+            args: args.to_vec(),
+            env,
+            cwd: env::current_dir().ok(),
+            fds,
+        })
+    }
+
+    // runs a builtin in a child process with the given stdin, stdout and stderr
+    fn fork_builtin(&mut self, command: &command::Command, fds: [Option<OwnedFd>; 3]) -> nix::Result<Pid> {
+        // output buffered by the shell would be written by the child as well
+        let _ = io::stdout().flush();
+        // fesh is single-threaded!
+        match unsafe { fork() }? {
+            ForkResult::Parent { child } => Ok(child),
+            ForkResult::Child => {
+                signals::reset_for_child();
+                self.traps.clear_for_subshell();
+                let mut io = BuiltinIo::new(fds);
+                // the child exits after the builtin, the assignments don't need to be undone
+                let status = match self.apply_assignments(&command.assignments) {
+                    Ok(()) => self.execute_buitin(command, &mut io),
+                    Err(e) => {
+                        self.logger.write_error(&mut io.stderr, e);
+                        1
+                    }
+                };
+                io.flush();
+                // skip destructors and exit handlers of the shell
+                unsafe { nix::libc::_exit(status) }
+            }
+        }
+    }
+
+    fn apply_assignments(&mut self, assignments: &[Assignment]) -> Result<(), String> {
+        assignments.iter().try_for_each(|a| self.expander.apply_assignment(a))
+    }
+
+    // waits for a stage, returns its exit status and the resources it used
+    fn wait(&mut self, stage: Stage) -> io::Result<(i32, Usage)> {
+        let (exit_status, usage) = match stage {
+            Stage::Process(pid) => self.executor.wait(pid)?,
+            Stage::Forked(pid) => executor::wait_pid(pid.as_raw())?,
+        };
+        Ok((Self::exit_status(exit_status), usage))
+    }
+
+    // applies the redirects of a command in order to its stdin, stdout and stderr
+    fn open_redirects(&self, redirects: &[Redirect], fds: &mut [Option<OwnedFd>; 3]) -> Result<(), String> {
+        for redirect in redirects {
+            let Some(file) = &redirect.file else {
+                return Err(format!("{}: ambiguous redirect", redirect.target.text()));
+            };
+            let fd = match usize::try_from(redirect.fd) {
+                Ok(fd) if fd < fds.len() => fd,
+                _ => return Err(format!("{}: only the file descriptors 0, 1 and 2 can be redirected", redirect.fd)),
+            };
+            self.logger.print_debug(
+                String::from("Fesh"),
+                format!("redirecting fd {} {:?} <{}>", fd, redirect.operator, file),
+            );
+
+            let path = path::Path::new(file);
+            let target: OwnedFd = match redirect.operator {
+                // `n>&m` makes n a copy of what m currently is
+                Operator::RedirectDuplicate => {
+                    let source = match file.parse::<usize>() {
+                        Ok(source) if source < fds.len() => source,
+                        _ => return Err(format!("{file}: bad file descriptor")),
+                    };
+                    let copy = match &fds[source] {
+                        Some(fd) => fd.try_clone(),
+                        None => Self::shell_fd(source),
+                    };
+                    copy.map_err(|e| format!("{file}: {e}"))?
+                }
+                Operator::RedirectInput => self.file_writer.open_input(path).map_err(|e| format!("{file}: {e}"))?.into(),
+                Operator::RedirectAppend => self.file_writer.open_append(path).map_err(|e| format!("{file}: {e}"))?.into(),
+                // with noclobber `>` never truncates an existing file
+                Operator::RedirectOverwrite if self.options.noclobber => {
+                    self.file_writer.open_noclobber(path).map_err(|e| format!("{file}: {e}"))?.into()
+                }
+                Operator::RedirectOverwrite | Operator::RedirectClobber | Operator::Pipe => {
+                    self.file_writer.open_overwrite(path).map_err(|e| format!("{file}: {e}"))?.into()
+                }
+            };
+            fds[fd] = Some(target);
+        }
+        Ok(())
+    }
+
+    // a copy of stdin, stdout or stderr of the shell
+    fn shell_fd(fd: usize) -> io::Result<OwnedFd> {
+        match fd {
+            0 => io::stdin().as_fd().try_clone_to_owned(),
+            1 => io::stdout().as_fd().try_clone_to_owned(),
+            _ => io::stderr().as_fd().try_clone_to_owned(),
+        }
+    }
+
+    // where an external command is run from, None if it can't be found, names with a `/`
+    // and commands with their own PATH are left to the spawn
+    fn find_program(&mut self, name: &str, assignments: &[Assignment]) -> Option<PathBuf> {
+        // `PATH=dir cmd` searches the given PATH
+        if name.contains('/') || assignments.iter().any(|a| a.name == "PATH") {
+            return Some(PathBuf::from(name));
+        }
+        self.command_hash.lookup(name)
+    }
+
+    fn command_not_found(&self, command: &str, stderr: &mut impl Write) {
+        let _ = match &self.logger.location {
+            Some(location) => writeln!(stderr, "fesh: {location}: {command}: command not found"),
+            None => writeln!(stderr, "fesh: {command}: command not found"),
+        };
+        let suggestions = self.suggestions(command);
+        if !suggestions.is_empty() {
+            let _ = writeln!(stderr, "did you mean: {}?", suggestions.join(", "));
+        }
+    }
+
+    // builtins, aliases and executables on PATH close to a mistyped command name,
+    // the closest first
+    fn suggestions(&self, command: &str) -> Vec<String> {
+        // one typo in short names, two in longer ones
+        let max_distance = if command.chars().count() < 5 { 1 } else { 2 };
+        let mut candidates: Vec<(usize, String)> = command::BUILTINS
+            .iter()
+            .map(|name| name.to_string())
+            .chain(self.config.aliases.keys().cloned())
+            .chain(util::executables_in_path())
+            .map(|name| (util::edit_distance(command, &name), name))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates.into_iter().take(MAX_SUGGESTIONS).map(|(_, name)| name).collect()
+    }
+
+    // prints why a command could not be started and returns the matching exit status
+    fn spawn_error_status(&self, command: &str, error: &io::Error, stderr: &mut impl Write) -> i32 {
+        match error.kind() {
+            io::ErrorKind::NotFound => {
+                self.command_not_found(command, stderr);
+                STATUS_NOT_FOUND
+            }
+            io::ErrorKind::PermissionDenied => {
+                self.logger.write_error(stderr, format!("{command}: permission denied"));
+                STATUS_NOT_EXECUTABLE
+            }
+            _ => {
+                self.logger
+                    .write_error(stderr, format!("failed to spawn child process <{command}>: {error}"));
+                STATUS_NOT_EXECUTABLE
+            }
+        }
+    }
+
+    fn exit_status(exit_status: ExitStatus) -> i32 {
+        match (exit_status.code(), exit_status.signal()) {
+            (Some(code), _) => code,
+            (None, Some(signal)) => STATUS_SIGNAL_BASE + signal,
+            (None, None) => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs::{self, File};
+    use std::io::Read;
+    use std::os::fd::BorrowedFd;
+
+    use super::*;
+    use crate::config_parser;
+    use crate::executor::FakeExecutor;
+
+    // a shell for a test, external commands only go to the fake executor, the path is a file
+    // name unique to the test which is removed again when the test ends
+    struct Fixture {
+        fesh: Fesh<'static>,
+        fake: FakeExecutor,
+        path: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            Self::with_config(name, config_parser::DEFAULT_CONFIG)
+        }
+
+        fn with_config(name: &str, config: &str) -> Self {
+            // the shell borrows the config for as long as it lives
+            let config: config::Config = toml::from_str(config).unwrap();
+            let mut fesh = Fesh::new(Box::leak(Box::new(config)));
+            let fake = FakeExecutor::new();
+            fesh.executor = Box::new(fake.clone());
+            let path = env::temp_dir().join(format!("fesh-{name}-{}", std::process::id()));
+            Fixture { fesh, fake, path }
+        }
+
+        // runs a script like the shell does and returns the status of its last command
+        fn run(&mut self, script: &str) -> i32 {
+            for command_list in self.fesh.input_parser.parse_script(script).unwrap() {
+                self.fesh.execute_line(command_list);
+                if let Some(status) = self.fesh.exiting {
+                    return status;
+                }
+                self.fesh.run_pending_traps();
+            }
+            self.fesh.last_status()
+        }
+
+        fn scalar(&self, name: &str) -> Option<String> {
+            self.fesh.expander.variables.get_scalar(name)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    #[test]
+    fn test_exit_status() {
+        // raw wait statuses: the exit code is in the second byte, a signal in the first
+        assert_eq!(Fesh::exit_status(ExitStatus::from_raw(0)), 0);
+        assert_eq!(Fesh::exit_status(ExitStatus::from_raw(3 << 8)), 3);
+        assert_eq!(Fesh::exit_status(ExitStatus::from_raw(9)), 137);
+    }
+
+    #[test]
+    fn test_pipeline_status() {
+        assert_eq!(Fesh::pipeline_status(&[1, 0], false, false), 0);
+        assert_eq!(Fesh::pipeline_status(&[2, 1, 0], true, false), 1);
+        assert_eq!(Fesh::pipeline_status(&[0, 0], true, false), 0);
+        assert_eq!(Fesh::pipeline_status(&[1, 0], true, true), 0);
+        assert_eq!(Fesh::pipeline_status(&[3], false, true), 0);
+        assert_eq!(Fesh::pipeline_status(&[0], false, true), 1);
+        // a line with only a comment
+        assert_eq!(Fesh::pipeline_status(&[], true, false), 0);
+    }
+
+    #[test]
+    fn test_pipeline_wiring() {
+        let mut shell = Fixture::new("wiring");
+
+        let line = format!("GREETING=hi /fake/a x 'y z' | /fake/b > {} 2>&1", shell.path.display());
+        assert_eq!(shell.run(&line), 0);
+        let mut spawned = shell.fake.take_spawned();
+        assert_eq!(spawned.len(), 2);
+        assert_eq!(spawned[0].program, PathBuf::from("/fake/a"));
+        assert_eq!(spawned[0].args, vec!["x", "y z"]);
+        assert_eq!(spawned[0].env, vec![(String::from("GREETING"), String::from("hi"))]);
+        assert_eq!(spawned[0].cwd, env::current_dir().ok());
+        assert!(spawned[1].env.is_empty());
+
+        // stdout of a is the pipe b reads from
+        let [a_in, a_out, a_err] = std::mem::take(&mut spawned[0].fds);
+        assert!(a_in.is_none() && a_err.is_none());
+        File::from(a_out.unwrap()).write_all(b"piped").unwrap();
+        let [b_in, b_out, b_err] = std::mem::take(&mut spawned[1].fds);
+        let mut piped = String::new();
+        File::from(b_in.unwrap()).read_to_string(&mut piped).unwrap();
+        assert_eq!(piped, "piped");
+
+        // stderr of b is a copy of its stdout, the file
+        File::from(b_out.unwrap()).write_all(b"out ").unwrap();
+        File::from(b_err.unwrap()).write_all(b"err").unwrap();
+        assert_eq!(fs::read_to_string(&shell.path).unwrap(), "out err");
+    }
+
+    #[test]
+    fn test_pipeline_exit_statuses() {
+        let mut shell = Fixture::new("exit-statuses");
+        shell.fake.exit_with("/fake/false", 3);
+
+        assert_eq!(shell.run("/fake/false | /fake/true"), 0);
+        assert_eq!(shell.run("! /fake/false"), 0);
+        assert_eq!(shell.run("set -o pipefail"), 0);
+        assert_eq!(shell.run("/fake/false | /fake/true"), 3);
+        let pipestatus = BTreeMap::from([(0, String::from("3")), (1, String::from("0"))]);
+        assert_eq!(shell.fesh.expander.variables.get("PIPESTATUS"), Some(&Value::Indexed(pipestatus)));
+        assert_eq!(shell.fake.take_spawned().len(), 5);
+
+        // a missing command is never spawned, the error goes to the stderr of the stage
+        let line = format!("fesh-missing-command 2> {}", shell.path.display());
+        assert_eq!(shell.run(&line), STATUS_NOT_FOUND);
+        assert!(shell.fake.take_spawned().is_empty());
+        let error = fs::read_to_string(&shell.path).unwrap();
+        assert!(error.starts_with("fesh: fesh-missing-command: command not found\n"));
+    }
+
+    #[test]
+    fn test_errexit() {
+        let mut shell = Fixture::new("errexit");
+        shell.fake.exit_with("/fake/false", 3);
+
+        // a negated failure doesn't stop the script, the EXIT trap runs before it stops
+        let script = "trap /fake/on-exit EXIT\nset -e\n! /fake/false\n/fake/false\n/fake/a";
+        assert_eq!(shell.run(script), 3);
+        let spawned: Vec<String> = shell.fake.take_spawned().into_iter().map(|spawn| spawn.arg0).collect();
+        assert_eq!(spawned, vec!["/fake/false", "/fake/false", "/fake/on-exit"]);
+        assert_eq!(shell.fesh.exiting, Some(3));
+    }
+
+    #[test]
+    fn test_xtrace() {
+        let mut shell = Fixture::new("xtrace");
+        shell.fesh.xtrace_output = Output::File(File::create(&shell.path).unwrap());
+
+        assert_eq!(shell.run("set -x\nGREETING=hi /fake/a 'b c' | /fake/b\nset +x\n/fake/c"), 0);
+        let trace = fs::read_to_string(&shell.path).unwrap();
+        assert_eq!(trace, "+ GREETING=hi /fake/a 'b c'\n+ /fake/b\n+ set +x\n");
+    }
+
+    #[test]
+    fn test_export() {
+        let mut shell = Fixture::new("export");
+
+        // a name without a value is exported once it is set
+        assert_eq!(shell.run("export FESH_TEST_PENDING"), 0);
+        assert_eq!(shell.run(&format!("export -p > {}", shell.path.display())), 0);
+        let exported = fs::read_to_string(&shell.path).unwrap();
+        assert!(exported.lines().any(|line| line == "declare -x FESH_TEST_PENDING"));
+        assert_eq!(shell.run("FESH_TEST_PENDING=1"), 0);
+        assert_eq!(env::var("FESH_TEST_PENDING").as_deref(), Ok("1"));
+        assert_eq!(shell.run("unset FESH_TEST_PENDING"), 0);
+
+        assert_eq!(shell.run("export -x"), STATUS_USAGE);
+        assert_eq!(shell.run("export 1x"), 1);
+    }
+
+    #[test]
+    fn test_exec_not_found() {
+        let mut shell = Fixture::new("exec");
+        let identity = |fd: BorrowedFd| stat::fstat(fd).map(|st| (st.st_dev, st.st_ino)).unwrap();
+        let shell_fds = || [identity(io::stdout().as_fd()), identity(io::stderr().as_fd())];
+        let before = shell_fds();
+
+        // the redirects of a command that can't run are not installed
+        let status = shell.run(&format!("exec /fake/missing > {} 2>&1\n/fake/a", shell.path.display()));
+        assert_eq!(status, STATUS_NOT_FOUND);
+        assert_eq!(shell_fds(), before);
+        assert!(fs::read_to_string(&shell.path).unwrap().contains("/fake/missing: command not found"));
+        assert!(shell.fake.take_spawned().is_empty());
+
+        shell.fesh.exiting = None;
+        let status = shell.run(&format!("exec {} 2> /dev/null", shell.path.display()));
+        assert_eq!(status, STATUS_NOT_EXECUTABLE);
+        assert_eq!(shell_fds(), before);
+    }
+
+    #[test]
+    fn test_redirect_above_stderr() {
+        let mut shell = Fixture::new("redirect-fd");
+
+        // fds above 2 are rejected before anything is opened
+        assert_eq!(shell.run(&format!("exec 3> {}", shell.path.display())), 1);
+        assert_eq!(shell.run(&format!("/fake/a 4< {}", shell.path.display())), 1);
+        assert!(!shell.path.exists());
+        assert!(shell.fake.take_spawned().is_empty());
+
+        let mut redirect = Redirect::new(Some(3), Operator::RedirectOverwrite, Word::literal("file"));
+        redirect.file = Some(String::from("file"));
+        let error = shell.fesh.open_redirects(&[redirect], &mut [None, None, None]);
+        assert_eq!(error, Err(String::from("3: only the file descriptors 0, 1 and 2 can be redirected")));
+    }
+
+    #[test]
+    fn test_resolution_order() {
+        let config = config_parser::DEFAULT_CONFIG.replace("[aliases]\n", "[aliases]\nread = \"read -r\"\n");
+        let mut shell = Fixture::with_config("resolve", &config);
+        let output = shell.path.display().to_string();
+        let mut lines = |script: &str| {
+            assert_eq!(shell.run(&format!("{script} > {output}")), 0);
+            fs::read_to_string(&output).unwrap().lines().map(String::from).collect::<Vec<_>>()
+        };
+
+        // alias, builtin, hashed path, then PATH
+        assert_eq!(lines("hash -p /bin/true read\nhash -p /bin/true sh"), Vec::<String>::new());
+        let read = lines("which -a read");
+        assert_eq!(read[..3], ["read: aliased to read -r", "read: shell built-in command", "/bin/true"]);
+        let on_path: Vec<String> = util::find_all_in_path("sh")
+            .into_iter()
+            .filter(|path| path != path::Path::new("/bin/true"))
+            .map(|path| path.display().to_string())
+            .collect();
+        assert!(!on_path.is_empty());
+        assert_eq!(lines("which -a sh"), [vec![String::from("/bin/true")], on_path].concat());
+
+        // without -a only the first one
+        assert_eq!(lines("which read"), ["read: aliased to read -r"]);
+        assert_eq!(lines("type read sh"), ["read is aliased to `read -r'", "sh is hashed (/bin/true)"]);
+        assert_eq!(lines("command -v sh"), ["/bin/true"]);
+
+        assert_eq!(shell.run("which -x sh"), STATUS_USAGE);
+    }
+
+    #[test]
+    fn test_declare() {
+        let mut shell = Fixture::new("declare");
+
+        assert_eq!(shell.run("declare -a arr=(a b)"), 0);
+        assert_eq!(shell.run("arr[-1]=c"), 0);
+        // an array keeps its kind and its elements
+        assert_eq!(shell.run("declare -A arr"), 1);
+        assert_eq!(shell.run("declare -A arr=([k]=v)"), 1);
+        let elements = BTreeMap::from([(0, String::from("a")), (1, String::from("c"))]);
+        assert_eq!(shell.fesh.expander.variables.get("arr"), Some(&Value::Indexed(elements)));
+
+        assert_eq!(shell.run("declare -A map"), 0);
+        assert_eq!(shell.run("declare -a map"), 1);
+        assert_eq!(shell.fesh.expander.variables.get("map"), Some(&Value::Associative(BTreeMap::new())));
+    }
+
+    #[test]
+    fn test_unset() {
+        let mut shell = Fixture::new("unset");
+
+        assert_eq!(shell.run("x=1\narr=(a b c)\ndeclare -A map=([k]=v [l]=w)"), 0);
+        assert_eq!(shell.run("unset x 'arr[1]' 'map[k]'"), 0);
+        assert_eq!(shell.scalar("x"), None);
+        let elements = BTreeMap::from([(0, String::from("a")), (2, String::from("c"))]);
+        assert_eq!(shell.fesh.expander.variables.get("arr"), Some(&Value::Indexed(elements)));
+        let entries = BTreeMap::from([(String::from("l"), String::from("w"))]);
+        assert_eq!(shell.fesh.expander.variables.get("map"), Some(&Value::Associative(entries)));
+
+        assert_eq!(shell.run("unset -v 'arr[@]' map"), 0);
+        assert_eq!(shell.fesh.expander.variables.get("arr"), None);
+        assert_eq!(shell.fesh.expander.variables.get("map"), None);
+        assert_eq!(shell.run("unset 1x"), 1);
+        assert_eq!(shell.run("unset -f x"), STATUS_USAGE);
+    }
+
+    #[test]
+    fn test_eval() {
+        let mut shell = Fixture::new("eval");
+        shell.fake.exit_with("/fake/false", 5);
+
+        // like the output of tools like `ssh-agent -s`
+        let line = "eval 'AGENT_PID=17;' /fake/a '\"$AGENT_PID\"' '| /fake/b'";
+        assert_eq!(shell.run(line), 0);
+        let spawned = shell.fake.take_spawned();
+        assert_eq!(spawned.len(), 2);
+        assert_eq!(spawned[0].args, vec!["17"]);
+        assert_eq!(shell.scalar("AGENT_PID").as_deref(), Some("17"));
+
+        assert_eq!(shell.run("eval /fake/false"), 5);
+        assert_eq!(shell.run("eval '/fake/a $?'"), 0);
+        assert_eq!(shell.fake.take_spawned()[1].args, vec!["5"]);
+        assert_eq!(shell.run("/fake/false\neval"), 0);
+        assert_eq!(shell.run("eval '/fake/a |'"), STATUS_USAGE);
+        assert_eq!(shell.fake.take_spawned().len(), 1);
+    }
+
+    #[test]
+    fn test_read() {
+        let mut shell = Fixture::new("read");
+        fs::write(&shell.path, "  one two\\ three  four \nnext").unwrap();
+
+        assert_eq!(shell.run(&format!("read first rest < {}", shell.path.display())), 0);
+        assert_eq!(shell.scalar("first").as_deref(), Some("one"));
+        assert_eq!(shell.scalar("rest").as_deref(), Some("two three  four"));
+
+        assert_eq!(shell.run(&format!("read -r -a words < {}", shell.path.display())), 0);
+        let words = ["one", "two\\", "three", "four"].iter().map(|w| w.to_string()).enumerate().collect();
+        assert_eq!(shell.fesh.expander.variables.get("words"), Some(&Value::Indexed(words)));
+
+        // the last line has no newline, it is still read
+        assert_eq!(shell.run(&format!("read -d q < {}", shell.path.display())), 1);
+        assert_eq!(shell.scalar("REPLY").as_deref(), Some("  one two three  four \nnext"));
+        assert_eq!(shell.run("read 1x"), 1);
+
+        // IFS only splits differently for the one read
+        fs::write(&shell.path, "root:x:0:0 root:/root\n").unwrap();
+        let ifs = shell.scalar("IFS");
+        assert_eq!(shell.run(&format!("IFS=: read -r user _ uid rest < {}", shell.path.display())), 0);
+        assert_eq!(shell.scalar("uid").as_deref(), Some("0"));
+        assert_eq!(shell.scalar("rest").as_deref(), Some("0 root:/root"));
+        assert_eq!(shell.scalar("IFS"), ifs);
+        assert_eq!(shell.run(&format!("IFS=,\nIFS=: read -r user < {}", shell.path.display())), 0);
+        assert_eq!(shell.scalar("user").as_deref(), Some("root:x:0:0 root:/root"));
+        assert_eq!(shell.scalar("IFS").as_deref(), Some(","));
+
+        // a variable that is not exported stays out of the environment
+        assert_eq!(shell.run(&format!("FESH_TEST_READ=1 read -r user < {}", shell.path.display())), 0);
+        assert!(env::var("FESH_TEST_READ").is_err());
+        assert_eq!(shell.scalar("FESH_TEST_READ"), None);
+        // and one marked for export is still exported once it is set
+        assert_eq!(shell.run("export FESH_TEST_READ"), 0);
+        assert_eq!(shell.run(&format!("FESH_TEST_READ=1 read -r user < {}", shell.path.display())), 0);
+        assert!(env::var("FESH_TEST_READ").is_err());
+        assert_eq!(shell.run("FESH_TEST_READ=2"), 0);
+        assert_eq!(env::var("FESH_TEST_READ").as_deref(), Ok("2"));
+        assert_eq!(shell.run("unset FESH_TEST_READ"), 0);
+    }
+
+    #[test]
+    fn test_source() {
+        let mut shell = Fixture::new("source");
+        shell.fake.exit_with("/fake/false", 4);
+        fs::write(&shell.path, "# setup\nGREETING=hello\n/fake/a $1 \"$@\"\n\n/fake/false\n").unwrap();
+        shell.fesh.expander.variables.positional_params = vec![String::from("shell")];
+
+        assert_eq!(shell.run(&format!(". {} x y", shell.path.display())), 4);
+        let spawned = shell.fake.take_spawned();
+        assert_eq!(spawned[0].args, vec!["x", "x", "y"]);
+        assert_eq!(shell.scalar("GREETING").as_deref(), Some("hello"));
+        assert_eq!(shell.fesh.expander.variables.positional_params, vec!["shell"]);
+
+        // without args the file sees the parameters of the shell
+        assert_eq!(shell.run(&format!("source {}", shell.path.display())), 4);
+        assert_eq!(shell.fake.take_spawned()[0].args, vec!["shell", "shell"]);
+
+        fs::write(&shell.path, "/fake/a\n/fake/a |\n").unwrap();
+        assert_eq!(shell.run(&format!("source {}", shell.path.display())), STATUS_USAGE);
+        assert!(shell.fake.take_spawned().is_empty());
+        assert_eq!(shell.run("source"), STATUS_USAGE);
+
+        fs::write(&shell.path, "/fake/a $?\n").unwrap();
+        assert_eq!(shell.run(&format!("/fake/false\nsource {}", shell.path.display())), 0);
+        assert_eq!(shell.fake.take_spawned()[1].args, vec!["4"]);
+        fs::write(&shell.path, "# nothing\n").unwrap();
+        assert_eq!(shell.run(&format!("/fake/false\nsource {}", shell.path.display())), 0);
     }
 }
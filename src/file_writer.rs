@@ -1,16 +1,41 @@
-use std::{fs, io::Write, path::Path};
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
 
 use crate::logger;
 
+// permissions of created files before the umask is applied
+const CREATE_MODE: u32 = 0o666;
+
 #[derive(Debug)]
 pub enum FileWriterError {
     Io(std::io::Error),
+    Exists, // noclobber refused to overwrite a file
 }
 
+impl fmt::Display for FileWriterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileWriterError::Io(e) => write!(f, "{}", e),
+            FileWriterError::Exists => write!(f, "cannot overwrite existing file"),
+        }
+    }
+}
+
+// opens redirect targets, the returned file is handed to the child process as is so
+// output is streamed to the file without being buffered by fesh
 pub struct FileWriter {
     pub logger: logger::Logger,
 }
 
+impl Default for FileWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FileWriter {
     pub fn new() -> FileWriter {
         FileWriter {
@@ -18,28 +43,93 @@ impl FileWriter {
         }
     }
 
-    pub fn overwrite_file(&self, path: &Path, content: &str) -> Result<usize, FileWriterError> {
-        let mut file = fs::OpenOptions::new()
+    // opens the file for `<`
+    pub fn open_input(&self, path: &Path) -> Result<File, FileWriterError> {
+        self.logger.print_debug(String::from("FileWriter"), format!("opening <{}> for reading", path.display()));
+
+        File::open(path).map_err(FileWriterError::Io)
+    }
+
+    // opens the file for `>`, creating or truncating it
+    pub fn open_overwrite(&self, path: &Path) -> Result<File, FileWriterError> {
+        self.logger.print_debug(String::from("FileWriter"), format!("opening <{}> for overwriting", path.display()));
+
+        fs::OpenOptions::new()
             .write(true)
             .truncate(true)
             .create(true)
+            .mode(CREATE_MODE)
             .open(path)
-            .map_err(FileWriterError::Io)?;
+            .map_err(FileWriterError::Io)
+    }
 
-        self.logger.print_debug(String::from("FileWriter"), format!("overwriting file <{}>", path.display()));
+    // opens the file for `>` with noclobber, only a new file is created and an existing regular
+    // file is never truncated, O_EXCL makes the check atomic
+    pub fn open_noclobber(&self, path: &Path) -> Result<File, FileWriterError> {
+        self.logger.print_debug(String::from("FileWriter"), format!("opening <{}> without clobbering", path.display()));
 
-        file.write(content.as_bytes()).map_err(FileWriterError::Io)
+        let created = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(CREATE_MODE)
+            .open(path);
+        match created {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                // files like /dev/null can still be written to, the check is on the opened file
+                let file = fs::OpenOptions::new().write(true).open(path).map_err(FileWriterError::Io)?;
+                match file.metadata() {
+                    Ok(metadata) if metadata.is_file() => Err(FileWriterError::Exists),
+                    Ok(_) => Ok(file),
+                    Err(e) => Err(FileWriterError::Io(e)),
+                }
+            }
+            result => result.map_err(FileWriterError::Io),
+        }
     }
 
-    pub fn append_to_file(&self, path: &Path, content: &str) -> Result<usize, FileWriterError> {
-        let mut file = fs::OpenOptions::new()
+    // opens the file for `>>`, creating it if needed, every write goes to the end of the file
+    pub fn open_append(&self, path: &Path) -> Result<File, FileWriterError> {
+        self.logger.print_debug(String::from("FileWriter"), format!("opening <{}> for appending", path.display()));
+
+        fs::OpenOptions::new()
             .append(true)
             .create(true)
+            .mode(CREATE_MODE)
             .open(path)
-            .map_err(FileWriterError::Io)?;
+            .map_err(FileWriterError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_overwrite_and_append() {
+        let path = std::env::temp_dir().join(format!("fesh-file-writer-{}", std::process::id()));
+        let file_writer = FileWriter::new();
+
+        file_writer.open_overwrite(&path).unwrap().write_all(b"first\n").unwrap();
+        file_writer.open_append(&path).unwrap().write_all(b"\xff\x00binary\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"first\n\xff\x00binary\n");
+
+        file_writer.open_overwrite(&path).unwrap().write_all(b"second\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_noclobber() {
+        let path = std::env::temp_dir().join(format!("fesh-noclobber-{}", std::process::id()));
+        let file_writer = FileWriter::new();
 
-        self.logger.print_debug(String::from("FileWriter"), format!("appending to file <{}>", path.display()));
+        file_writer.open_noclobber(&path).unwrap().write_all(b"kept\n").unwrap();
+        assert!(matches!(file_writer.open_noclobber(&path), Err(FileWriterError::Exists)));
+        assert_eq!(fs::read(&path).unwrap(), b"kept\n");
+        assert!(file_writer.open_noclobber(Path::new("/dev/null")).is_ok());
 
-        file.write(content.as_bytes()).map_err(FileWriterError::Io)
+        fs::remove_file(&path).unwrap();
     }
 }
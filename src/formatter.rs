@@ -0,0 +1,243 @@
+use crate::command::assignment::{Assignment, AssignmentValue};
+use crate::command::command::Command;
+use crate::command::command_error::CommandError;
+use crate::command::command_list::{CommandList, Time};
+use crate::command::operator::Operator;
+use crate::command::redirect::Redirect;
+use crate::command::token::Token;
+use crate::command::word::{Word, WordPart};
+use crate::input_parser;
+use crate::logger;
+use crate::util;
+
+// pretty-prints fesh scripts: one command list per line, single spaces between words and
+// operators, at most one blank line in a row, comments and quoting are kept as written
+pub struct Formatter {
+    pub logger: logger::Logger,
+    input_parser: input_parser::InputParser,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Formatter {
+            logger: logger::Logger::new(false),
+            input_parser: input_parser::InputParser::new(),
+        }
+    }
+
+    pub fn format(&self, input: &str) -> Result<String, CommandError> {
+        let mut lines: Vec<String> = Vec::new();
+        let mut line: Vec<Token> = Vec::new();
+        let mut blank = false;
+
+        let mut tokens = self.input_parser.tokenizer.tokenize(input)?;
+        tokens.push(Token::Newline);
+
+        for token in tokens {
+            if token != Token::Newline {
+                line.push(token);
+                continue;
+            }
+            if line.is_empty() {
+                blank = !lines.is_empty();
+                continue;
+            }
+            if blank {
+                lines.push(String::new());
+                blank = false;
+            }
+            let command_list = self.input_parser.parse_tokens(std::mem::take(&mut line))?;
+            lines.push(Self::format_command_list(&command_list));
+        }
+
+        self.logger
+            .print_debug(String::from("Formatter"), format!("formatted {} lines", lines.len()));
+
+        if lines.is_empty() {
+            return Ok(String::new());
+        }
+        Ok(lines.join("\n") + "\n")
+    }
+
+    pub fn format_command_list(command_list: &CommandList) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        let mut operators = command_list.operators.iter();
+
+        match command_list.time {
+            Some(Time::Default) => parts.push(String::from("time")),
+            Some(Time::Posix) => parts.push(String::from("time -p")),
+            None => {}
+        }
+        if command_list.negated {
+            parts.push(String::from("!"));
+        }
+        for (i, command) in command_list.commands.iter().enumerate() {
+            if i > 0
+                && let Some(operator) = operators.next()
+            {
+                parts.push(Self::format_operator(operator).to_string());
+            }
+            parts.push(Self::format_command(command));
+        }
+
+        if let Some(comment) = &command_list.comment {
+            parts.push(format!("#{}", comment));
+        }
+        parts.join(" ")
+    }
+
+    // redirects are written after the words, wherever they appeared in the input
+    pub fn format_command(command: &Command) -> String {
+        let assignments = command.assignments.iter().map(Self::format_assignment);
+        let words = command.words.iter().map(Self::format_word);
+        let redirects = command.redirects.iter().map(Self::format_redirect);
+        assignments.chain(words).chain(redirects).collect::<Vec<String>>().join(" ")
+    }
+
+    fn format_redirect(redirect: &Redirect) -> String {
+        let fd = if redirect.is_default_fd() { String::new() } else { redirect.fd.to_string() };
+        let separator = if redirect.operator == Operator::RedirectDuplicate { "" } else { " " };
+        format!(
+            "{}{}{}{}",
+            fd,
+            Self::format_operator(&redirect.operator),
+            separator,
+            Self::format_word(&redirect.target)
+        )
+    }
+
+    pub fn format_operator(operator: &Operator) -> &'static str {
+        match operator {
+            Operator::Pipe => "|",
+            Operator::RedirectOverwrite => ">",
+            Operator::RedirectAppend => ">>",
+            Operator::RedirectInput => "<",
+            Operator::RedirectDuplicate => ">&",
+            Operator::RedirectClobber => ">|",
+        }
+    }
+
+    fn format_assignment(assignment: &Assignment) -> String {
+        let mut text = assignment.name.clone();
+        if let Some(subscript) = &assignment.subscript {
+            text.push_str(&format!("[{}]", Self::format_parts(&subscript.parts, false, false)));
+        }
+        text.push_str(if assignment.append { "+=" } else { "=" });
+        match &assignment.value {
+            AssignmentValue::Scalar(word) => text.push_str(&Self::format_parts(&word.parts, false, false)),
+            AssignmentValue::Array(elements) => text.push_str(&Self::format_array(elements)),
+        }
+        text
+    }
+
+    fn format_array(elements: &[Word]) -> String {
+        let elements: Vec<String> = elements.iter().map(Self::format_word).collect();
+        format!("({})", elements.join(" "))
+    }
+
+    pub fn format_word(word: &Word) -> String {
+        Self::format_parts(&word.parts, false, true)
+    }
+
+    // `word_start` is false for parts that follow other text, e.g. the value of an assignment
+    fn format_parts(parts: &[WordPart], quoted: bool, word_start: bool) -> String {
+        let mut text = String::new();
+        for (i, part) in parts.iter().enumerate() {
+            match part {
+                WordPart::Literal(s) if !quoted && Self::needs_quoting(s, word_start && i == 0, i + 1 == parts.len()) => text.push_str(&util::quote(s)),
+                WordPart::Literal(s) => text.push_str(s),
+                WordPart::Escaped(c) => {
+                    text.push('\\');
+                    text.push(*c);
+                }
+                WordPart::SingleQuoted(s) => text.push_str(&format!("'{}'", s)),
+                WordPart::DoubleQuoted(inner) => text.push_str(&format!("\"{}\"", Self::format_parts(inner, true, false))),
+                WordPart::Parameter(parameter) => {
+                    // $name followed by a name character needs braces
+                    let next_continues_name = matches!(
+                        parts.get(i + 1),
+                        Some(WordPart::Literal(s)) if s.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+                    );
+                    let name_is_word = parameter.name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+                    if parameter.is_simple() && name_is_word && next_continues_name {
+                        text.push_str(&format!("${{{}}}", parameter.name));
+                    } else {
+                        text.push_str(&parameter.source());
+                    }
+                }
+                WordPart::Tilde => text.push('~'),
+                WordPart::Array(elements) => text.push_str(&Self::format_array(elements)),
+            }
+        }
+        text
+    }
+
+    // literals built outside of the parser (e.g. `Command::new`) may contain special characters
+    fn needs_quoting(text: &str, first: bool, last: bool) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        for (i, c) in chars.iter().enumerate() {
+            match c {
+                ' ' | '\t' | '\n' | ';' | '|' | '>' | '<' | '\'' | '"' | '\\' => return true,
+                '$' if matches!(chars.get(i + 1), Some(n) if n.is_ascii_alphanumeric() || "_@*#?{".contains(*n)) => {
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        // a lone `~` followed by a quote or parameter is not a tilde prefix
+        first && (text.starts_with('#') || (text == "~" && last) || text.starts_with("~/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip(input: &str) {
+        let formatter = Formatter::new();
+        let parser = input_parser::InputParser::new();
+        let formatted = formatter.format(input).unwrap();
+        assert_eq!(parser.parse_script(&formatted).unwrap(), parser.parse_script(input).unwrap());
+        assert_eq!(formatter.format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_spacing() {
+        let formatter = Formatter::new();
+        let formatted = formatter.format("  ls   -la|grep  foo>>out.txt  ").unwrap();
+        assert_eq!(formatted, "ls -la | grep foo >> out.txt\n");
+    }
+
+    #[test]
+    fn test_format_keeps_comments_and_single_blank_lines() {
+        let formatter = Formatter::new();
+        let formatted = formatter.format("\n#!/usr/bin/env fesh\n\n\n# setup\nFOO=bar   # trailing\n\n").unwrap();
+        assert_eq!(formatted, "#!/usr/bin/env fesh\n\n# setup\nFOO=bar # trailing\n");
+    }
+
+    #[test]
+    fn test_format_keeps_quoting() {
+        let formatter = Formatter::new();
+        let formatted = formatter.format("echo  'a  b'  \"$HOME\"/x  \\$y ${z}w arr=( 1  \"2 3\" )").unwrap();
+        assert_eq!(formatted, "echo 'a  b' \"$HOME\"/x \\$y ${z}w arr=(1 \"2 3\")\n");
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        assert_round_trip("# comment\nls -la | wc -l > out # trailing\n\necho \"${arr[@]:1:2}\" ${#x} $1 \"$@\"\n");
+        assert_round_trip("arr=(a 'b c' [4]=d)\nm[$k]+=v\necho ~ ~/x a~ $ $% \"$\" x\\ y\n");
+        assert_round_trip("echo \"multi\nline\" \\\n  continued\n");
+        assert_round_trip("a=# b=~ ~\"x\" $\\\nHOME\necho trailing\\");
+        assert_round_trip("> out echo 2 2>&1 <in | sort 2>>err -r\n");
+        assert_round_trip("echo a >| out 2>|err | wc\n");
+        assert_round_trip("cd src; ls; echo 'a;b'\n");
+        assert_round_trip("! grep -q x file | cat\n! ! echo !\n'!' x\n");
+        assert_round_trip("time -p ! make | tail\ntime\ntime -p -p\necho time\n! time x\n");
+    }
+}
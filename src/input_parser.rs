@@ -1,110 +1,188 @@
-use std::env;
-
 use crate::command::command::Command;
 use crate::command::command_error::CommandError;
-use crate::command::command_list::CommandList;
+use crate::command::command_list::{CommandList, Time};
 use crate::command::operator::Operator;
+use crate::command::redirect::Redirect;
+use crate::command::token::Token;
+use crate::command::word::Word;
+use crate::expander;
+use crate::formatter::Formatter;
 use crate::logger;
+use crate::tokenizer;
 
 pub struct InputParser {
     pub logger: logger::Logger,
+    pub tokenizer: tokenizer::Tokenizer,
+}
+
+impl Default for InputParser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl InputParser {
     pub fn new() -> Self {
         InputParser {
             logger: logger::Logger::new(false),
+            tokenizer: tokenizer::Tokenizer::new(),
         }
     }
 
+    // parses and expands the input using the environment
     pub fn parse(&self, input: String) -> Result<CommandList, CommandError> {
-        let operator_set = ["|", ">", ">>"];
-        let mut commands: Vec<Command> = Vec::new();
-        let mut operators: Vec<Operator> = Vec::new();
+        let mut command_list = self.parse_raw(input)?;
+        expander::Expander::new().expand_command_list(&mut command_list);
+        Ok(command_list)
+    }
 
-        if input.is_empty() {
-            self.logger.print_debug(String::from("InputParser"), format!("input is empty"));
+    // parses a single command list without expanding the words, input with several lists
+    // separated by `;` or newlines needs parse_script
+    pub fn parse_raw(&self, input: String) -> Result<CommandList, CommandError> {
+        if input.trim().is_empty() {
+            self.logger.print_debug(String::from("InputParser"), String::from("input is empty"));
             return Err(CommandError::Empty);
         }
 
-        let mut current_cmd: Vec<String> = Vec::new();
+        let tokens = self.tokenizer.tokenize(&input)?;
+        // separators before or after the list are fine, between commands they end the list
+        let start = tokens.iter().position(|t| *t != Token::Newline).unwrap_or(tokens.len());
+        let end = tokens.iter().rposition(|t| *t != Token::Newline).map_or(start, |end| end + 1);
+        if tokens[start..end].contains(&Token::Newline) {
+            return Err(CommandError::UnexpectedToken(String::from(";")));
+        }
+        self.parse_tokens(tokens[start..end].to_vec())
+    }
+
+    // parses a script, every non-empty line is a command list
+    pub fn parse_script(&self, input: &str) -> Result<Vec<CommandList>, CommandError> {
+        let command_lists = self.parse_script_numbered(input).map_err(|(_, e)| e)?;
+        Ok(command_lists.into_iter().map(|(_, command_list)| command_list).collect())
+    }
 
-        let parts = input.split_whitespace();
-        for part in parts {
-            if operator_set.contains(&part) {
-                if !current_cmd.is_empty() {
-                    let cmd = Command::new(current_cmd[0].clone(), current_cmd[1..].to_vec());
-                    commands.push(cmd);
-                    current_cmd.clear();
+    // like parse_script, with the line number every command list starts on, an error comes
+    // with the line it is on, the last line if the input ended too early like in a quote
+    pub fn parse_script_numbered(&self, input: &str) -> Result<Vec<(usize, CommandList)>, (usize, CommandError)> {
+        let last_line = input.lines().count().max(1);
+        let spanned = self.tokenizer.tokenize_spanned(input).map_err(|e| (last_line, e))?;
+
+        let mut command_lists: Vec<(usize, CommandList)> = Vec::new();
+        let mut line: Vec<Token> = Vec::new();
+        let mut line_number = 1;
+        for (token, span) in spanned {
+            if line.is_empty() {
+                line_number = span.line;
+            }
+            if token == Token::Newline {
+                if !line.is_empty() {
+                    let command_list = self.parse_tokens(std::mem::take(&mut line)).map_err(|e| (line_number, e))?;
+                    command_lists.push((line_number, command_list));
                 }
-                let op = match part {
-                    "|" => Operator::Pipe,
-                    ">" => Operator::RedirectOverwrite,
-                    ">>" => Operator::RedirectAppend,
-                    _ => continue,
-                };
-                operators.push(op);
             } else {
-                current_cmd.push(Self::expand(part));
+                line.push(token);
             }
         }
-
-        if !current_cmd.is_empty() {
-            let cmd = Command::new(current_cmd[0].clone(), current_cmd[1..].to_vec());
-            commands.push(cmd);
+        if !line.is_empty() {
+            command_lists.push((line_number, self.parse_tokens(line).map_err(|e| (line_number, e))?));
         }
 
-        self.logger.print_debug(String::from("InputParser"), format!("commands: {:?}", commands));
-        self.logger.print_debug(String::from("InputParser"), format!("operators: {:?}", operators));
-        Ok(CommandList::new(commands, operators))
+        Ok(command_lists)
     }
 
-    fn expand(input: &str) -> String {
-        let expanded = Self::expand_tilde(input);
-        Self::expand_env_vars(&expanded)
-    }
+    // parses the tokens of a single line, every `|` needs a command on both sides and every
+    // redirect operator a target word, redirects belong to the command they appear in
+    pub fn parse_tokens(&self, tokens: Vec<Token>) -> Result<CommandList, CommandError> {
+        let mut commands: Vec<Command> = Vec::new();
+        let mut operators: Vec<Operator> = Vec::new();
+        let mut current_cmd: Vec<Word> = Vec::new();
+        let mut redirects: Vec<Redirect> = Vec::new();
+        let mut io_number: Option<u32> = None;
+        let mut comment: Option<String> = None;
+        let mut negated = false;
+        let mut time: Option<Time> = None;
+        let mut tokens = tokens.into_iter();
 
-    fn expand_tilde(input: &str) -> String {
-        if input == "~" {
-            env::var("HOME").unwrap_or_else(|_| input.to_string())
-        } else if input.starts_with("~/") {
-            if let Ok(home) = env::var("HOME") {
-                format!("{}{}", home, &input[1..])
-            } else {
-                input.to_string()
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::Operator(op) if op.is_redirect() => {
+                    let target = match tokens.next() {
+                        Some(Token::Word(word)) => word,
+                        Some(token) => return Err(Self::unexpected(&token)),
+                        None => return Err(CommandError::UnexpectedToken(String::from("newline"))),
+                    };
+                    redirects.push(Redirect::new(io_number.take(), op, target));
+                }
+                Token::Operator(op) => {
+                    if current_cmd.is_empty() && redirects.is_empty() {
+                        return Err(Self::unexpected(&Token::Operator(op)));
+                    }
+                    commands.push(Self::command(std::mem::take(&mut current_cmd), std::mem::take(&mut redirects)));
+                    operators.push(op);
+                }
+                // the tokenizer only emits an io number right before a redirect operator
+                Token::IoNumber(fd) => io_number = Some(fd),
+                // `time [-p]` and `!` are only reserved words before the pipeline
+                Token::Word(word) if Self::is_reserved(&word, "time", time.is_none() && !negated, &commands, &current_cmd, &redirects) => {
+                    time = Some(Time::Default)
+                }
+                Token::Word(word) if Self::is_reserved(&word, "-p", time == Some(Time::Default) && !negated, &commands, &current_cmd, &redirects) => {
+                    time = Some(Time::Posix)
+                }
+                Token::Word(word) if Self::is_reserved(&word, "!", !negated, &commands, &current_cmd, &redirects) => {
+                    negated = true
+                }
+                Token::Word(word) => current_cmd.push(word),
+                Token::Comment(text) => comment = Some(text),
+                Token::Newline => {}
             }
-        } else {
-            input.to_string()
         }
-    }
-
-    fn expand_env_vars(input: &str) -> String {
-        let mut result = input.to_string();
-        let mut i = 0;
 
-        while let Some(start) = result[i..].find('$') {
-            let start = start + i;
-            let rest = &result[start + 1..];
+        if !current_cmd.is_empty() || !redirects.is_empty() {
+            commands.push(Self::command(current_cmd, redirects));
+        } else if !operators.is_empty() {
+            return Err(CommandError::UnexpectedToken(String::from("newline")));
+        }
 
-            let end = rest
-                .find(|c: char| !c.is_alphanumeric() && c != '_')
-                .unwrap_or(rest.len());
+        self.logger.print_debug(String::from("InputParser"), format!("commands: {:?}", commands));
+        self.logger.print_debug(String::from("InputParser"), format!("operators: {:?}", operators));
+        let mut command_list = CommandList::new(commands, operators);
+        command_list.comment = comment;
+        command_list.negated = negated;
+        command_list.time = time;
+        Ok(command_list)
+    }
 
-            if end == 0 {
-                i = start + 1;
-                continue;
-            }
+    // an unquoted reserved word that is allowed at this position, before the first command
+    fn is_reserved(
+        word: &Word,
+        reserved: &str,
+        allowed: bool,
+        commands: &[Command],
+        words: &[Word],
+        redirects: &[Redirect],
+    ) -> bool {
+        allowed
+            && commands.is_empty()
+            && words.is_empty()
+            && redirects.is_empty()
+            && word.is_plain_literal()
+            && word.text() == reserved
+    }
 
-            let var_name = &rest[..end];
-            if let Ok(value) = env::var(var_name) {
-                result = format!("{}{}{}", &result[..start], value, &rest[end..]);
-                i = start + value.len();
-            } else {
-                i = start + 1 + end;
-            }
-        }
+    fn command(words: Vec<Word>, redirects: Vec<Redirect>) -> Command {
+        let mut command = Command::from_words(words);
+        command.redirects = redirects;
+        command
+    }
 
-        result
+    fn unexpected(token: &Token) -> CommandError {
+        let text = match token {
+            Token::Operator(op) => Formatter::format_operator(op).to_string(),
+            Token::IoNumber(fd) => fd.to_string(),
+            Token::Word(word) => word.text(),
+            Token::Newline | Token::Comment(_) => String::from("newline"),
+        };
+        CommandError::UnexpectedToken(text)
     }
 }
 
@@ -187,12 +265,15 @@ mod tests {
         let result = parser.parse(String::from("echo hello > output.txt"));
         assert!(result.is_ok());
         let command_list = result.unwrap();
-        assert_eq!(command_list.commands.len(), 2);
-        assert_eq!(command_list.operators.len(), 1);
+        assert_eq!(command_list.commands.len(), 1);
+        assert_eq!(command_list.operators.len(), 0);
         assert_eq!(command_list.commands[0].command, "echo");
         assert_eq!(command_list.commands[0].args, vec!["hello"]);
-        assert_eq!(command_list.commands[1].command, "output.txt");
-        assert_eq!(command_list.operators[0], Operator::RedirectOverwrite);
+        let redirects = &command_list.commands[0].redirects;
+        assert_eq!(redirects.len(), 1);
+        assert_eq!(redirects[0].fd, 1);
+        assert_eq!(redirects[0].operator, Operator::RedirectOverwrite);
+        assert_eq!(redirects[0].file.as_deref(), Some("output.txt"));
     }
 
     #[test]
@@ -201,9 +282,52 @@ mod tests {
         let result = parser.parse(String::from("echo hello >> output.txt"));
         assert!(result.is_ok());
         let command_list = result.unwrap();
+        assert_eq!(command_list.commands.len(), 1);
+        assert_eq!(command_list.operators.len(), 0);
+        assert_eq!(command_list.commands[0].redirects[0].operator, Operator::RedirectAppend);
+    }
+
+    #[test]
+    fn test_parse_redirects_anywhere() {
+        let parser = InputParser::new();
+        let command_list = parser.parse(String::from("> out.txt grep foo 2>>err file.txt")).unwrap();
+        assert_eq!(command_list.commands.len(), 1);
+        assert_eq!(command_list.commands[0].command, "grep");
+        assert_eq!(command_list.commands[0].args, vec!["foo", "file.txt"]);
+        let redirects: Vec<(u32, Operator, Option<&str>)> = command_list.commands[0]
+            .redirects
+            .iter()
+            .map(|r| (r.fd, r.operator, r.file.as_deref()))
+            .collect();
+        assert_eq!(
+            redirects,
+            vec![
+                (1, Operator::RedirectOverwrite, Some("out.txt")),
+                (2, Operator::RedirectAppend, Some("err")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_redirects_in_pipeline() {
+        let parser = InputParser::new();
+        let command_list = parser.parse(String::from("sort < in 2>&1 | uniq > out")).unwrap();
         assert_eq!(command_list.commands.len(), 2);
-        assert_eq!(command_list.operators.len(), 1);
-        assert_eq!(command_list.operators[0], Operator::RedirectAppend);
+        assert_eq!(command_list.operators, vec![Operator::Pipe]);
+        let first = &command_list.commands[0].redirects;
+        assert_eq!((first[0].fd, first[0].operator), (0, Operator::RedirectInput));
+        assert_eq!((first[1].fd, first[1].operator), (2, Operator::RedirectDuplicate));
+        assert_eq!(first[1].file.as_deref(), Some("1"));
+        assert_eq!(command_list.commands[1].redirects[0].file.as_deref(), Some("out"));
+    }
+
+    #[test]
+    fn test_parse_io_number_only_before_redirect() {
+        let parser = InputParser::new();
+        let command_list = parser.parse(String::from("echo 2 >out '2'>x a2>y")).unwrap();
+        assert_eq!(command_list.commands[0].args, vec!["2", "2", "a2"]);
+        let fds: Vec<u32> = command_list.commands[0].redirects.iter().map(|r| r.fd).collect();
+        assert_eq!(fds, vec![1, 1, 1]);
     }
 
     #[test]
@@ -234,4 +358,144 @@ mod tests {
         assert_eq!(command_list.commands[0].command, "ls");
         assert_eq!(command_list.commands[0].args, vec!["-la", "/tmp"]);
     }
+
+    #[test]
+    fn test_parse_quoted_words() {
+        let parser = InputParser::new();
+        let result = parser.parse(String::from("echo 'a  b' \"c d\" e\\ f"));
+        assert!(result.is_ok());
+        let command_list = result.unwrap();
+        assert_eq!(command_list.commands.len(), 1);
+        assert_eq!(command_list.commands[0].args, vec!["a  b", "c d", "e f"]);
+    }
+
+    #[test]
+    fn test_parse_quoted_empty_word() {
+        let parser = InputParser::new();
+        let result = parser.parse(String::from("echo '' \"\" x"));
+        assert!(result.is_ok());
+        let command_list = result.unwrap();
+        assert_eq!(command_list.commands[0].args, vec!["", "", "x"]);
+    }
+
+    #[test]
+    fn test_parse_quoted_operator() {
+        let parser = InputParser::new();
+        let result = parser.parse(String::from("echo '|' \">\""));
+        assert!(result.is_ok());
+        let command_list = result.unwrap();
+        assert_eq!(command_list.commands.len(), 1);
+        assert_eq!(command_list.operators.len(), 0);
+        assert_eq!(command_list.commands[0].args, vec!["|", ">"]);
+    }
+
+    #[test]
+    fn test_parse_unterminated_quote() {
+        let parser = InputParser::new();
+        let result = parser.parse(String::from("echo 'abc"));
+        assert_eq!(result.unwrap_err(), CommandError::UnterminatedQuote('\''));
+    }
+
+    #[test]
+    fn test_parse_dangling_operator() {
+        let parser = InputParser::new();
+        assert_eq!(
+            parser.parse(String::from("ls >")).unwrap_err(),
+            CommandError::UnexpectedToken(String::from("newline"))
+        );
+        assert_eq!(
+            parser.parse(String::from("| wc")).unwrap_err(),
+            CommandError::UnexpectedToken(String::from("|"))
+        );
+        assert_eq!(
+            parser.parse(String::from("ls > | wc")).unwrap_err(),
+            CommandError::UnexpectedToken(String::from("|"))
+        );
+        assert_eq!(
+            parser.parse(String::from("ls 2> # comment")).unwrap_err(),
+            CommandError::UnexpectedToken(String::from("newline"))
+        );
+    }
+
+    #[test]
+    fn test_parse_negated_pipeline() {
+        let parser = InputParser::new();
+        let command_list = parser.parse(String::from("! grep x | wc -l")).unwrap();
+        assert!(command_list.negated);
+        assert_eq!(command_list.commands[0].command, "grep");
+
+        // only a leading unquoted `!` negates
+        let command_list = parser.parse(String::from("echo !")).unwrap();
+        assert!(!command_list.negated);
+        assert_eq!(command_list.commands[0].args, vec!["!"]);
+        let command_list = parser.parse(String::from("'!' x")).unwrap();
+        assert!(!command_list.negated);
+        assert_eq!(command_list.commands[0].command, "!");
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let parser = InputParser::new();
+        let command_list = parser.parse(String::from("time -p ! cargo build | tail")).unwrap();
+        assert_eq!(command_list.time, Some(Time::Posix));
+        assert!(command_list.negated);
+        assert_eq!(command_list.commands.len(), 2);
+        assert_eq!(command_list.commands[0].args, vec!["build"]);
+
+        let command_list = parser.parse(String::from("time ls -p")).unwrap();
+        assert_eq!(command_list.time, Some(Time::Default));
+        assert_eq!(command_list.commands[0].args, vec!["-p"]);
+
+        // `time` after `!` or quoted is a command
+        let command_list = parser.parse(String::from("! time")).unwrap();
+        assert_eq!(command_list.time, None);
+        assert_eq!(command_list.commands[0].command, "time");
+    }
+
+    #[test]
+    fn test_strip_command_prefix() {
+        let parser = InputParser::new();
+        let mut command_list = parser.parse(String::from("command -- declare -a x")).unwrap();
+        let command = &mut command_list.commands[0];
+        command.strip_command_prefix();
+        assert_eq!(command.command, "declare");
+        assert_eq!(command.args, vec!["-a", "x"]);
+        assert_eq!(command.command_type, CommandType::Builtin);
+        assert_eq!(command.words.len(), 3);
+
+        // `command -v` is the builtin itself
+        let mut command_list = parser.parse(String::from("command -v ls")).unwrap();
+        let command = &mut command_list.commands[0];
+        command.strip_command_prefix();
+        assert_eq!(command.command, "command");
+        assert_eq!(command.args, vec!["-v", "ls"]);
+    }
+
+    #[test]
+    fn test_parse_script_lines() {
+        let parser = InputParser::new();
+        let result = parser.parse_script("ls -la\n\necho 'multi\nline' | wc -l\n");
+        assert!(result.is_ok());
+        let command_lists = result.unwrap();
+        assert_eq!(command_lists.len(), 2);
+        assert_eq!(command_lists[0].commands[0].command, "ls");
+        assert_eq!(command_lists[1].commands[0].args, vec!["multi\nline"]);
+        assert_eq!(command_lists[1].operators, vec![Operator::Pipe]);
+
+        let numbered = parser.parse_script_numbered("ls\n\necho 'a\nb'\nwc\n").unwrap();
+        let line_numbers: Vec<usize> = numbered.iter().map(|(line, _)| *line).collect();
+        assert_eq!(line_numbers, vec![1, 3, 5]);
+        assert_eq!(parser.parse_script_numbered("ls\nls |\n").unwrap_err().0, 2);
+        assert_eq!(parser.parse_script_numbered("ls\necho 'a\n\n").unwrap_err().0, 3);
+
+        let command_lists = parser.parse_script("cd src; ls;\necho 'a;b' a\\;b").unwrap();
+        assert_eq!(command_lists.len(), 3);
+        assert_eq!(command_lists[1].commands[0].command, "ls");
+        assert_eq!(command_lists[2].commands[0].args, vec!["a;b", "a;b"]);
+
+        // a single list can't hold several commands in a row
+        let error = CommandError::UnexpectedToken(String::from(";"));
+        assert_eq!(parser.parse_raw(String::from("echo a; rm x")), Err(error));
+        assert_eq!(parser.parse_raw(String::from("echo a;")).unwrap().commands[0].args, vec!["a"]);
+    }
 }
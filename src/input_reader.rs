@@ -44,6 +44,12 @@ impl Highlighter for FeshHelper {
     }
 }
 
+pub enum Line {
+    Input(String),
+    Interrupted, // Ctrl + c
+    Eof,         // Ctrl + d
+}
+
 pub struct InputReader<'a> {
     pub logger: logger::Logger,
     readline_config: &'a config::ReadlineConfig,
@@ -59,7 +65,7 @@ impl<'a> InputReader<'a> {
         }
     }
 
-    pub fn readline(&self, prompt: &prompt::Prompt) -> String {
+    pub fn readline(&self, prompt: &prompt::Prompt) -> Line {
         let edit_mode = match self.readline_config.edit_mode.as_str() {
             "emacs" => EditMode::Emacs,
             "vi" => EditMode::Vi,
@@ -89,7 +95,7 @@ impl<'a> InputReader<'a> {
         rl.set_helper(Some(helper));
 
         if rl.load_history(&self.history_path).is_err() {
-            self.logger.print_debug(String::from("InputReader"), format!("no previous history found"));
+            self.logger.print_debug(String::from("InputReader"), String::from("no previous history found"));
         }
 
         let readline = rl.readline(&prompt.get_colored_prompt());
@@ -97,25 +103,30 @@ impl<'a> InputReader<'a> {
             Ok(line) => {
                 let _ = rl.add_history_entry(line.as_str());
                 self.save_history(&mut rl);
-                line
+                Line::Input(line)
             },
+            // Ctrl + c
+            Err(ReadlineError::Interrupted) => Line::Interrupted,
             // Ctrl + d
             Err(ReadlineError::Eof) => {
                 self.save_history(&mut rl);
-                exit(0);
+                Line::Eof
+            }
+            // e.g. the terminal is gone
+            Err(e) => {
+                self.logger.print_error(format!("failed to read input: {e}"));
+                Line::Eof
             }
-            _ => "".to_string(),
         }
     }
 
     fn save_history<H: rustyline::Helper>(&self, rl: &mut Editor<H, rustyline::history::DefaultHistory>) {
-        if let Some(parent) = self.history_path.parent() {
-            if !parent.exists() {
-                if let Err(e) = fs::create_dir_all(parent) {
-                    self.logger.print_debug(String::from("InputReader"), format!("history directory cant be created: {e}"));
-                    return;
-                }
-            }
+        if let Some(parent) = self.history_path.parent()
+            && !parent.exists()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            self.logger.print_debug(String::from("InputReader"), format!("history directory cant be created: {e}"));
+            return;
         }
         
         if let Err(e) = rl.save_history(&self.history_path) {
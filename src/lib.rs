@@ -0,0 +1,27 @@
+// this tells the compile to include the files, the modules are a library so fuzz targets and
+// integration tests can use them
+pub mod builtin_io; // builtin_io.rs
+pub mod command; // command/*
+pub mod command_hash; // command_hash.rs
+pub mod config; // config.rs
+pub mod config_parser; // config_parser.rs
+pub mod executor; // executor.rs
+pub mod expander; // expander.rs
+pub mod fesh; // fesh.rs
+pub mod file_writer; // file_writer.rs
+pub mod formatter; // formatter.rs
+pub mod input_parser; // input_parser.rs
+pub mod input_reader; // input_reader.rs
+pub mod limits; // limits.rs
+pub mod linter; // linter.rs
+pub mod logger; // logger.rs
+pub mod mode; // mode.rs
+pub mod options; // options.rs
+pub mod prompt; // prompt.rs
+pub mod read_line; // read_line.rs
+pub mod signals; // signals.rs
+pub mod timing; // timing.rs
+pub mod tokenizer; // tokenizer.rs
+pub mod traps; // traps.rs
+pub mod util; // util.rs
+pub mod variables; // variables.rs
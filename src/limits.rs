@@ -0,0 +1,107 @@
+use nix::sys::resource::{RLIM_INFINITY, Resource, getrlimit, rlim_t, setrlimit};
+
+// a resource limit of `ulimit`, shown and set in multiples of unit
+pub struct Limit {
+    pub flag: char,
+    pub description: &'static str,
+    pub unit: &'static str,
+    scale: rlim_t,
+    pub resource: Resource,
+}
+
+const fn limit(flag: char, description: &'static str, unit: &'static str, scale: rlim_t, resource: Resource) -> Limit {
+    Limit {
+        flag,
+        description,
+        unit,
+        scale,
+        resource,
+    }
+}
+
+// the limits in the order `ulimit -a` lists them, sizes are in KiB like in bash
+pub const LIMITS: [Limit; 15] = [
+    limit('c', "core file size", "blocks", 1024, Resource::RLIMIT_CORE),
+    limit('d', "data seg size", "kbytes", 1024, Resource::RLIMIT_DATA),
+    limit('e', "scheduling priority", "", 1, Resource::RLIMIT_NICE),
+    limit('f', "file size", "blocks", 1024, Resource::RLIMIT_FSIZE),
+    limit('i', "pending signals", "", 1, Resource::RLIMIT_SIGPENDING),
+    limit('l', "max locked memory", "kbytes", 1024, Resource::RLIMIT_MEMLOCK),
+    limit('m', "max memory size", "kbytes", 1024, Resource::RLIMIT_RSS),
+    limit('n', "open files", "", 1, Resource::RLIMIT_NOFILE),
+    limit('q', "POSIX message queues", "bytes", 1, Resource::RLIMIT_MSGQUEUE),
+    limit('r', "real-time priority", "", 1, Resource::RLIMIT_RTPRIO),
+    limit('s', "stack size", "kbytes", 1024, Resource::RLIMIT_STACK),
+    limit('t', "cpu time", "seconds", 1, Resource::RLIMIT_CPU),
+    limit('u', "max user processes", "", 1, Resource::RLIMIT_NPROC),
+    limit('v', "virtual memory", "kbytes", 1024, Resource::RLIMIT_AS),
+    limit('x', "file locks", "", 1, Resource::RLIMIT_LOCKS),
+];
+
+// the limit of `ulimit` without a resource flag, the file size
+pub const DEFAULT: &Limit = &LIMITS[3];
+
+// which of the two limits `ulimit -S` and `ulimit -H` refer to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    Soft,
+    Hard,
+    Both, // setting without -S or -H changes both, showing shows the soft limit
+}
+
+impl Limit {
+    pub fn from_flag(flag: char) -> Option<&'static Limit> {
+        LIMITS.iter().find(|limit| limit.flag == flag)
+    }
+
+    // the limit in units, or `unlimited`
+    pub fn get(&self, kind: Kind) -> nix::Result<String> {
+        let (soft, hard) = getrlimit(self.resource)?;
+        let value = if kind == Kind::Hard { hard } else { soft };
+        if value == RLIM_INFINITY {
+            return Ok(String::from("unlimited"));
+        }
+        Ok((value / self.scale).to_string())
+    }
+
+    // sets the limit from a number of units, `unlimited`, `soft` or `hard`
+    pub fn set(&self, kind: Kind, value: &str) -> Result<(), String> {
+        let (soft, hard) = getrlimit(self.resource).map_err(|e| e.to_string())?;
+        let value = match value {
+            "unlimited" => RLIM_INFINITY,
+            "soft" => soft,
+            "hard" => hard,
+            number => number
+                .parse::<rlim_t>()
+                .ok()
+                .and_then(|n| n.checked_mul(self.scale))
+                .ok_or_else(|| format!("{number}: invalid number"))?,
+        };
+        let (soft, hard) = match kind {
+            Kind::Soft => (value, hard),
+            Kind::Hard => (soft.min(value), value),
+            Kind::Both => (value, value),
+        };
+        setrlimit(self.resource, soft, hard).map_err(|e| format!("{}: cannot modify limit: {}", self.description, e))
+    }
+
+    // the line of `ulimit -a`, like `open files                          (-n) 1024`
+    pub fn describe(&self, value: &str) -> String {
+        let unit = if self.unit.is_empty() { format!("(-{})", self.flag) } else { format!("({}, -{})", self.unit, self.flag) };
+        format!("{:<28}{:>16} {}", self.description, unit, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let limit = Limit::from_flag('n').unwrap();
+        assert_eq!(limit.describe("1024"), "open files                              (-n) 1024");
+        let limit = Limit::from_flag('c').unwrap();
+        assert_eq!(limit.describe("0"), "core file size                  (blocks, -c) 0");
+        assert!(Limit::from_flag('z').is_none());
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::command::assignment::AssignmentValue;
+use crate::command::command::Command;
+use crate::command::command_error::CommandError;
+use crate::command::command_list::{CommandList, Time};
+use crate::command::span::Span;
+use crate::command::token::Token;
+use crate::command::word::{Parameter, Word, WordPart};
+use crate::input_parser;
+use crate::logger;
+use crate::util;
+
+// variables the shell itself reads, assigning them is never unused
+const SHELL_VARIABLES: [&str; 4] = ["IFS", "PATH", "HOME", "CDPATH"];
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub hint: String,
+}
+
+impl Diagnostic {
+    pub fn to_text(&self, file: &str) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        format!(
+            "{}:{}:{}: {}[{}]: {}\n  = hint: {}",
+            file, self.span.line, self.span.column, severity, self.code, self.message, self.hint
+        )
+    }
+}
+
+// a parsed line with the spans of its words and the rules disabled for it
+struct Line {
+    command_list: CommandList,
+    word_spans: Vec<Span>,
+    disabled: HashSet<String>,
+}
+
+// reports shellcheck-style diagnostics for fesh scripts, a comment like
+// `# fesh-lint disable=FL001,FL002` disables rules for its own line or, on a line of its own, the next line
+pub struct Linter<'a> {
+    pub logger: logger::Logger,
+    input_parser: input_parser::InputParser,
+    aliases: &'a HashMap<String, String>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(aliases: &'a HashMap<String, String>) -> Self {
+        Linter {
+            logger: logger::Logger::new(false),
+            input_parser: input_parser::InputParser::new(),
+            aliases,
+        }
+    }
+
+    pub fn lint(&self, input: &str) -> Result<Vec<Diagnostic>, CommandError> {
+        let lines = self.parse_lines(input)?;
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+        for line in &lines {
+            let mut line_diagnostics = Vec::new();
+            self.check_unquoted_expansions(line, &mut line_diagnostics);
+            self.check_commands_exist(line, &mut line_diagnostics);
+            diagnostics.extend(line_diagnostics.into_iter().filter(|d| !Self::is_disabled(line, d.code)));
+        }
+        self.check_unused_assignments(&lines, &mut diagnostics);
+
+        diagnostics.sort_by_key(|d| d.span.start);
+        self.logger
+            .print_debug(String::from("Linter"), format!("diagnostics: {:?}", diagnostics));
+        Ok(diagnostics)
+    }
+
+    fn parse_lines(&self, input: &str) -> Result<Vec<Line>, CommandError> {
+        let mut lines: Vec<Line> = Vec::new();
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut word_spans: Vec<Span> = Vec::new();
+        let mut disabled: HashSet<String> = HashSet::new();
+        let mut disabled_next: HashSet<String> = HashSet::new();
+        // redirect targets are not words of the command, their spans are not kept
+        let mut redirect_target = false;
+
+        let mut spanned = self.input_parser.tokenizer.tokenize_spanned(input)?;
+        spanned.push((Token::Newline, Span { start: 0, end: 0, line: 0, column: 0 }));
+
+        for (token, span) in spanned {
+            match token {
+                Token::Newline if tokens.is_empty() => {
+                    // a directive on a line of its own applies to the next line
+                    disabled_next.extend(disabled.drain());
+                }
+                Token::Newline => {
+                    disabled.extend(disabled_next.drain());
+                    lines.push(Line {
+                        command_list: self.input_parser.parse_tokens(std::mem::take(&mut tokens))?,
+                        word_spans: std::mem::take(&mut word_spans),
+                        disabled: std::mem::take(&mut disabled),
+                    });
+                }
+                Token::Comment(text) => disabled.extend(Self::parse_directive(&text)),
+                Token::Word(_) => {
+                    if !redirect_target {
+                        word_spans.push(span);
+                    }
+                    redirect_target = false;
+                    tokens.push(token);
+                }
+                Token::Operator(op) => {
+                    redirect_target = op.is_redirect();
+                    tokens.push(token);
+                }
+                Token::IoNumber(_) => tokens.push(token),
+            }
+        }
+        Ok(lines)
+    }
+
+    // parses `fesh-lint disable=FL001,FL002`
+    fn parse_directive(comment: &str) -> Vec<String> {
+        let Some(rest) = comment.trim().strip_prefix("fesh-lint") else {
+            return Vec::new();
+        };
+        let Some(codes) = rest.trim().strip_prefix("disable=") else {
+            return Vec::new();
+        };
+        codes.split(',').map(|code| code.trim().to_string()).collect()
+    }
+
+    fn is_disabled(line: &Line, code: &str) -> bool {
+        line.disabled.contains(code)
+    }
+
+    // the commands of a line with the spans of their words (assignments come first)
+    fn commands_with_spans(line: &Line) -> Vec<(&Command, &[Span])> {
+        let mut commands = Vec::new();
+        // skip the `time [-p]` and `!` before the pipeline
+        let reserved = match line.command_list.time {
+            Some(Time::Default) => 1,
+            Some(Time::Posix) => 2,
+            None => 0,
+        } + line.command_list.negated as usize;
+        let mut offset = reserved.min(line.word_spans.len());
+        for command in &line.command_list.commands {
+            let count = command.assignments.len() + command.words.len();
+            let end = (offset + count).min(line.word_spans.len());
+            commands.push((command, &line.word_spans[offset..end]));
+            offset = end;
+        }
+        commands
+    }
+
+    // FL001: unquoted expansions are split into fields and vanish when empty
+    fn check_unquoted_expansions(&self, line: &Line, diagnostics: &mut Vec<Diagnostic>) {
+        for (command, spans) in Self::commands_with_spans(line) {
+            let word_spans = &spans[command.assignments.len().min(spans.len())..];
+            for (word, span) in command.words.iter().zip(word_spans) {
+                for part in &word.parts {
+                    let WordPart::Parameter(parameter) = part else {
+                        continue;
+                    };
+                    // lengths and counts never contain whitespace
+                    if parameter.length || parameter.name == "#" {
+                        continue;
+                    }
+                    let source = parameter.source();
+                    diagnostics.push(Diagnostic {
+                        code: "FL001",
+                        severity: Severity::Warning,
+                        message: format!("unquoted expansion `{}` is split into fields and removed when empty", source),
+                        span: *span,
+                        hint: format!("double quote it: \"{}\"", source),
+                    });
+                }
+            }
+        }
+    }
+
+    // FL003: commands that are neither builtins, aliases nor executables on PATH
+    fn check_commands_exist(&self, line: &Line, diagnostics: &mut Vec<Diagnostic>) {
+        for (command, spans) in Self::commands_with_spans(line) {
+            let Some(word) = command.words.first() else {
+                continue;
+            };
+            // names built from expansions can't be checked statically
+            if !Self::is_static(word) {
+                continue;
+            }
+
+            let name = word.text();
+            let found = if name.contains('/') {
+                util::is_executable(Path::new(&name))
+            } else {
+                Command::is_builtin(&name) || self.aliases.contains_key(&name) || util::find_in_path(&name).is_some()
+            };
+            if found {
+                continue;
+            }
+
+            let Some(span) = spans.get(command.assignments.len()) else {
+                continue;
+            };
+            diagnostics.push(Diagnostic {
+                code: "FL003",
+                severity: Severity::Error,
+                message: format!("command `{}` not found", name),
+                span: *span,
+                hint: if name.contains('/') {
+                    String::from("check that the file exists and is executable")
+                } else {
+                    String::from("check the spelling or install the command, it is not a builtin, alias or on PATH")
+                },
+            });
+        }
+    }
+
+    // FL002: variables that are assigned but never expanded anywhere in the script
+    fn check_unused_assignments(&self, lines: &[Line], diagnostics: &mut Vec<Diagnostic>) {
+        let mut used: HashSet<String> = HashSet::new();
+        for line in lines {
+            for command in &line.command_list.commands {
+                for word in &command.words {
+                    Self::collect_references(&word.parts, &mut used);
+                }
+                // `declare -p name` and `export name` refer to name
+                if command.words.first().is_some_and(|w| matches!(w.text().as_str(), "declare" | "export")) {
+                    used.extend(command.words.iter().map(|w| w.text()));
+                }
+                for assignment in &command.assignments {
+                    for word in assignment.subscript.iter() {
+                        Self::collect_references(&word.parts, &mut used);
+                    }
+                    match &assignment.value {
+                        AssignmentValue::Scalar(word) => {
+                            Self::collect_references(&word.parts, &mut used)
+                        }
+                        AssignmentValue::Array(elements) => {
+                            for element in elements {
+                                Self::collect_references(&element.parts, &mut used);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut reported: HashSet<String> = HashSet::new();
+        for line in lines {
+            for (command, spans) in Self::commands_with_spans(line) {
+                // `name=value cmd` only sets the environment of cmd
+                if !command.words.is_empty() {
+                    continue;
+                }
+                for (assignment, span) in command.assignments.iter().zip(spans) {
+                    let name = &assignment.name;
+                    if used.contains(name)
+                        || reported.contains(name)
+                        || SHELL_VARIABLES.contains(&name.as_str())
+                        || env::var_os(name).is_some()
+                        || Self::is_disabled(line, "FL002")
+                    {
+                        continue;
+                    }
+                    reported.insert(name.clone());
+                    diagnostics.push(Diagnostic {
+                        code: "FL002",
+                        severity: Severity::Warning,
+                        message: format!("`{}` is assigned but never used", name),
+                        span: *span,
+                        hint: format!("remove the assignment, or use `{}=... cmd` to pass it to a command", name),
+                    });
+                }
+            }
+        }
+    }
+
+    fn collect_references(parts: &[WordPart], used: &mut HashSet<String>) {
+        for part in parts {
+            match part {
+                WordPart::Parameter(parameter) => Self::collect_parameter_references(parameter, used),
+                WordPart::DoubleQuoted(inner) => Self::collect_references(inner, used),
+                WordPart::Array(elements) => {
+                    for element in elements {
+                        Self::collect_references(&element.parts, used);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // subscripts and slices may use variables by their bare name, like ${arr[i]}
+    fn collect_parameter_references(parameter: &Parameter, used: &mut HashSet<String>) {
+        used.insert(parameter.name.clone());
+        let mut texts: Vec<&String> = parameter.subscript.iter().collect();
+        if let Some((offset, count)) = &parameter.slice {
+            texts.push(offset);
+            texts.extend(count.iter());
+        }
+        for text in texts {
+            let names = text
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .filter(|name| !name.is_empty());
+            used.extend(names.map(String::from));
+        }
+    }
+
+    // true if the word has no expansions
+    fn is_static(word: &Word) -> bool {
+        word.parts.iter().all(|part| match part {
+            WordPart::Literal(_) | WordPart::Escaped(_) | WordPart::SingleQuoted(_) => true,
+            WordPart::DoubleQuoted(inner) => Self::is_static(&Word::new(inner.clone())),
+            _ => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(input: &str) -> Vec<&'static str> {
+        let aliases = HashMap::from([(String::from("ll"), String::from("ls -lah"))]);
+        let linter = Linter::new(&aliases);
+        linter.lint(input).unwrap().into_iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_unquoted_expansion() {
+        assert_eq!(codes("echo $HOME \"$HOME\" ${#HOME}\n"), vec!["FL001"]);
+    }
+
+    #[test]
+    fn test_unused_assignment() {
+        assert_eq!(codes("fesh_unused=1\nfesh_used=2\necho \"$fesh_used\"\n"), vec!["FL002"]);
+        assert!(codes("i=1\narr=(a b)\necho \"${arr[i]}\"\n").is_empty());
+    }
+
+    #[test]
+    fn test_command_not_found() {
+        assert_eq!(codes("fesh-no-such-command -x\ncd /tmp\nll\n"), vec!["FL003"]);
+    }
+
+    #[test]
+    fn test_suppression() {
+        assert!(codes("echo $HOME # fesh-lint disable=FL001\n").is_empty());
+        assert!(codes("# fesh-lint disable=FL001,FL003\nfesh-no-such-command $HOME\n").is_empty());
+        assert_eq!(codes("# fesh-lint disable=FL001\n\necho $HOME\necho $HOME\n"), vec!["FL001"]);
+    }
+
+    #[test]
+    fn test_span() {
+        let aliases = HashMap::new();
+        let diagnostics = Linter::new(&aliases).lint("cd /\n  echo $HOME\n").unwrap();
+        assert_eq!(diagnostics[0].span, Span { start: 12, end: 17, line: 2, column: 8 });
+    }
+}
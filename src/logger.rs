@@ -1,12 +1,19 @@
+use std::io::Write;
+
 use colored::Colorize;
 
 pub struct Logger {
     pub debug_enabled: bool,
+    // where errors happen, like `file:line` of a sourced file
+    pub location: Option<String>,
 }
 
 impl Logger {
     pub fn new(enabled: bool) -> Self {
-        Logger { debug_enabled: enabled }
+        Logger {
+            debug_enabled: enabled,
+            location: None,
+        }
     }
 
     pub fn toggle_debug(&mut self) {
@@ -14,7 +21,19 @@ impl Logger {
     }
 
     pub fn print_error(&self, text: String) {
-        eprintln!("[{}] {}", "ERROR".red(), text);
+        eprintln!("[{}] {}", "ERROR".red(), self.located(text));
+    }
+
+    // like print_error, for builtins whose stderr may be redirected
+    pub fn write_error(&self, out: &mut impl Write, text: String) {
+        let _ = writeln!(out, "[{}] {}", "ERROR".red(), self.located(text));
+    }
+
+    fn located(&self, text: String) -> String {
+        match &self.location {
+            Some(location) => format!("{location}: {text}"),
+            None => text,
+        }
     }
 
     pub fn print_debug(&self, part: String, text: String) {
@@ -1,30 +1,259 @@
-// this tells the compile to include the files
-pub mod command; // command/*
-pub mod config; // config.rs
-pub mod config_parser; // config_parser.rs
-pub mod fesh; // fesh.rs
-pub mod file_writer; // file_writer.rs
-pub mod input_parser; // input_parser.rs
-pub mod input_reader; // input_reader.rs
-pub mod logger; // logger.rs
-pub mod prompt; // prompt.rs
-pub mod util; // util.rs
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::exit;
 
 use clap::Parser;
-use fesh::Fesh;
+use fesh::fesh::Fesh;
+use fesh::{config, config_parser, formatter, input_parser, linter, options, util};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
-struct Args {}
+#[command(args_conflicts_with_subcommands = true, subcommand_precedence_over_arg = true)]
+#[command(group(clap::ArgGroup::new("input").args(["command", "args"]).multiple(true)))]
+#[command(group(clap::ArgGroup::new("dump").args(["dump_tokens", "dump_ast"])))]
+struct Args {
+    /// Print the tokens of the input as JSON and exit
+    #[arg(long, requires = "input")]
+    dump_tokens: bool,
+
+    /// Print the parse tree of the input as JSON and exit
+    #[arg(long, requires = "input")]
+    dump_ast: bool,
+
+    /// Run a command string non-interactively
+    #[arg(short = 'c', value_name = "COMMAND")]
+    command: Option<String>,
+
+    /// Exit when a command fails, like `set -e`
+    #[arg(short = 'e')]
+    errexit: bool,
+
+    /// Treat expanding an unset variable as an error, like `set -u`
+    #[arg(short = 'u')]
+    nounset: bool,
+
+    /// Print every command before running it, like `set -x`
+    #[arg(short = 'x')]
+    xtrace: bool,
+
+    /// Enable a shell option, like `set -o OPTION`
+    #[arg(short = 'o', value_name = "OPTION")]
+    options: Vec<String>,
+
+    /// Run a script file non-interactively, the remaining arguments are its positional
+    /// parameters ($1, $2, ...), with -c all arguments are positional parameters
+    #[arg(value_name = "FILE [ARGS]", trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+
+    #[command(subcommand)]
+    subcommand: Option<Subcommands>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Subcommands {
+    /// Format fesh scripts in place, or stdin to stdout if no files are given
+    Fmt {
+        /// Don't write the files, exit with 1 if any file is not formatted
+        #[arg(long)]
+        check: bool,
+
+        /// Files to format
+        files: Vec<PathBuf>,
+    },
+    /// Report problems in fesh scripts, reads stdin if no files are given
+    Lint {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = LintFormat::Text)]
+        format: LintFormat,
+
+        /// Files to lint
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum LintFormat {
+    Text,
+    Json,
+}
+
+#[derive(serde::Serialize)]
+struct FileDiagnostic<'a> {
+    file: String,
+    #[serde(flatten)]
+    diagnostic: &'a linter::Diagnostic,
+}
 
 fn parse_args() -> Args {
     Args::parse()
 }
 
+// applies the shell options given on the command line on top of the ones from the config
+fn apply_options(args: &Args, options: &mut options::Options) -> Result<(), String> {
+    let flags = [("errexit", args.errexit), ("nounset", args.nounset), ("xtrace", args.xtrace)];
+    for (name, _) in flags.iter().filter(|(_, enabled)| *enabled) {
+        options.set(name, true)?;
+    }
+    for name in &args.options {
+        options.set(name, true)?;
+    }
+    Ok(())
+}
+
+// returns the command string or the content of the script file and the positional parameters
+fn read_input(args: &Args) -> (String, Vec<String>) {
+    if let Some(command) = &args.command {
+        return (command.clone(), args.args.clone());
+    }
+    let (file, params) = args.args.split_first().expect("clap requires an input");
+    match fs::read_to_string(file) {
+        Ok(s) => (s, params.to_vec()),
+        Err(e) => util::exit_with_error(&format!("{}: {}", file, e)),
+    }
+}
+
+// prints the tokens or the parse tree (with aliases replaced) of the input as JSON
+fn dump(args: &Args, config: &config::Config) {
+    let (input, _) = read_input(args);
+    let parser = input_parser::InputParser::new();
+
+    let json = if args.dump_tokens {
+        parser.tokenizer.tokenize(&input).map(|tokens| serde_json::to_string_pretty(&tokens))
+    } else {
+        parser.parse_script(&input).map(|mut command_lists| {
+            for command_list in command_lists.iter_mut() {
+                command_list.replace_aliases(&config.aliases);
+            }
+            serde_json::to_string_pretty(&command_lists)
+        })
+    };
+
+    match json {
+        Ok(Ok(json)) => println!("{}", json),
+        Ok(Err(e)) => util::exit_with_error(&e.to_string()),
+        Err(e) => util::exit_with_error(&format!("syntax error: {}", e)),
+    }
+}
+
+// formats the files and returns the exit code
+fn fmt(check: bool, files: &[PathBuf]) -> i32 {
+    let formatter = formatter::Formatter::new();
+
+    if files.is_empty() {
+        let input = read_stdin();
+        return match formatter.format(&input) {
+            Ok(formatted) if check => (formatted != input) as i32,
+            Ok(formatted) => {
+                print!("{}", formatted);
+                0
+            }
+            Err(e) => util::exit_with_error(&format!("<stdin>: syntax error: {}", e)),
+        };
+    }
+
+    let mut exit_code = 0;
+    for file in files {
+        let input = match fs::read_to_string(file) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("error: {}: {}", file.display(), e);
+                exit_code = 1;
+                continue;
+            }
+        };
+        let formatted = match formatter.format(&input) {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("error: {}: syntax error: {}", file.display(), e);
+                exit_code = 1;
+                continue;
+            }
+        };
+        if formatted == input {
+            continue;
+        }
+        if check {
+            println!("would reformat {}", file.display());
+            exit_code = 1;
+        } else if let Err(e) = fs::write(file, formatted) {
+            eprintln!("error: {}: {}", file.display(), e);
+            exit_code = 1;
+        }
+    }
+    exit_code
+}
+
+fn read_stdin() -> String {
+    let mut input = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+        util::exit_with_error(&format!("stdin: {}", e));
+    }
+    input
+}
+
+// lints the files and returns the exit code, 1 if there are any diagnostics
+fn lint(format: LintFormat, files: &[PathBuf], config: &config::Config) -> i32 {
+    let linter = linter::Linter::new(&config.aliases);
+
+    let inputs: Vec<(String, Result<String, String>)> = if files.is_empty() {
+        vec![(String::from("<stdin>"), Ok(read_stdin()))]
+    } else {
+        files
+            .iter()
+            .map(|f| (f.display().to_string(), fs::read_to_string(f).map_err(|e| e.to_string())))
+            .collect()
+    };
+
+    let mut exit_code = 0;
+    let mut results: Vec<(String, Vec<linter::Diagnostic>)> = Vec::new();
+    for (file, input) in inputs {
+        match input.map(|input| linter.lint(&input).map_err(|e| format!("syntax error: {}", e))) {
+            Ok(Ok(diagnostics)) => {
+                if !diagnostics.is_empty() {
+                    exit_code = 1;
+                }
+                results.push((file, diagnostics));
+            }
+            Ok(Err(e)) | Err(e) => {
+                eprintln!("error: {}: {}", file, e);
+                exit_code = 1;
+            }
+        }
+    }
+
+    match format {
+        LintFormat::Text => {
+            for (file, diagnostics) in &results {
+                for diagnostic in diagnostics {
+                    println!("{}", diagnostic.to_text(file));
+                }
+            }
+        }
+        LintFormat::Json => {
+            let diagnostics: Vec<FileDiagnostic> = results
+                .iter()
+                .flat_map(|(file, diagnostics)| {
+                    diagnostics.iter().map(|diagnostic| FileDiagnostic { file: file.clone(), diagnostic })
+                })
+                .collect();
+            match serde_json::to_string_pretty(&diagnostics) {
+                Ok(json) => println!("{}", json),
+                Err(e) => util::exit_with_error(&e.to_string()),
+            }
+        }
+    }
+    exit_code
+}
+
 fn main() {
-    let _args = parse_args();
+    let args = parse_args();
+
+    if let Some(Subcommands::Fmt { check, files }) = &args.subcommand {
+        exit(fmt(*check, files));
+    }
 
-    let config = match config_parser::ConfigParser::new().read() {
+    let mut config = match config_parser::ConfigParser::new().read() {
         Ok(c) => c,
         Err(e) => {
             eprintln!("error: {}", e);
@@ -32,6 +261,23 @@ fn main() {
         }
     };
 
+    if let Some(Subcommands::Lint { format, files }) = &args.subcommand {
+        exit(lint(*format, files, &config));
+    }
+
+    if args.dump_tokens || args.dump_ast {
+        dump(&args, &config);
+        return;
+    }
+
+    if let Err(e) = apply_options(&args, &mut config.options) {
+        util::exit_with_error(&e);
+    }
+
     let mut fesh = Fesh::new(&config);
-    fesh.run();
+    if args.command.is_some() || !args.args.is_empty() {
+        let (input, params) = read_input(&args);
+        exit(fesh.run_script(&input, params));
+    }
+    exit(fesh.run());
 }
@@ -0,0 +1,100 @@
+// the permission bits of `u`, `g` and `o` in a mode
+const WHO_BITS: [(char, u32); 3] = [('u', 0o700), ('g', 0o070), ('o', 0o007)];
+
+// parses the argument of umask, an octal mask like `022` or the permissions new files may
+// get like `u=rwx,g=rx,o=` or `g-w`, returns the new mask
+pub fn parse_umask(text: &str, current: u32) -> Result<u32, String> {
+    if text.starts_with(|c: char| c.is_ascii_digit()) {
+        return match u32::from_str_radix(text, 8) {
+            Ok(mask) if mask <= 0o777 => Ok(mask),
+            _ => Err(format!("{text}: octal number out of range")),
+        };
+    }
+
+    let mut allowed = !current & 0o777;
+    for clause in text.split(',') {
+        let invalid = || format!("{text}: invalid symbolic mode");
+        let ops_start = clause.find(['+', '-', '=']).ok_or_else(invalid)?;
+        let (who, mut ops) = clause.split_at(ops_start);
+
+        let mut who_bits = 0;
+        for c in who.chars() {
+            who_bits |= match c {
+                'a' => 0o777,
+                c => WHO_BITS.iter().find(|(w, _)| *w == c).map(|(_, bits)| *bits).ok_or_else(invalid)?,
+            };
+        }
+        if who.is_empty() {
+            who_bits = 0o777;
+        }
+
+        // a clause can have several operators like `u+r-w`
+        while let Some(op) = ops.chars().next() {
+            let perms_end = ops[1..].find(['+', '-', '=']).map(|i| i + 1).unwrap_or(ops.len());
+            let mut perm_bits = 0;
+            for c in ops[1..perms_end].chars() {
+                perm_bits |= match c {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    _ => return Err(invalid()),
+                };
+            }
+            let bits = perm_bits & who_bits;
+            match op {
+                '+' => allowed |= bits,
+                '-' => allowed &= !bits,
+                _ => allowed = (allowed & !who_bits) | bits,
+            }
+            ops = &ops[perms_end..];
+        }
+    }
+    Ok(!allowed & 0o777)
+}
+
+// the permissions a mask allows, like `u=rwx,g=rx,o=rx` for 022
+pub fn symbolic(mask: u32) -> String {
+    let allowed = !mask & 0o777;
+    let clauses: Vec<String> = WHO_BITS
+        .iter()
+        .map(|(who, bits)| {
+            let perms: String = [('r', 0o444), ('w', 0o222), ('x', 0o111)]
+                .iter()
+                .filter(|(_, perm)| allowed & bits & perm != 0)
+                .map(|(c, _)| *c)
+                .collect();
+            format!("{who}={perms}")
+        })
+        .collect();
+    clauses.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_umask() {
+        assert_eq!(parse_umask("077", 0o022), Ok(0o077));
+        assert_eq!(parse_umask("0", 0o022), Ok(0));
+        assert!(parse_umask("1000", 0o022).is_err());
+        assert!(parse_umask("8", 0o022).is_err());
+
+        assert_eq!(parse_umask("u=rwx,g=rx,o=", 0o000), Ok(0o027));
+        assert_eq!(parse_umask("g-w", 0o002), Ok(0o022));
+        assert_eq!(parse_umask("o+r", 0o077), Ok(0o073));
+        assert_eq!(parse_umask("a=rx", 0o000), Ok(0o222));
+        assert_eq!(parse_umask("=", 0o022), Ok(0o777));
+        assert_eq!(parse_umask("ug=rw-w+x,o-rwx", 0o000), Ok(0o227));
+        assert!(parse_umask("u=rwz", 0o022).is_err());
+        assert!(parse_umask("q=r", 0o022).is_err());
+        assert!(parse_umask("rw", 0o022).is_err());
+    }
+
+    #[test]
+    fn test_symbolic() {
+        assert_eq!(symbolic(0o022), "u=rwx,g=rx,o=rx");
+        assert_eq!(symbolic(0o077), "u=rwx,g=,o=");
+        assert_eq!(symbolic(0o777), "u=,g=,o=");
+    }
+}
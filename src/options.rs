@@ -0,0 +1,52 @@
+use serde::Deserialize;
+
+// shell options, set in the options section of the config, on the command line or with
+// `set -o name`
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Options {
+    pub errexit: bool,   // set -e, exit when a command fails
+    pub noclobber: bool, // set -C, `>` doesn't overwrite existing files
+    pub nounset: bool,   // set -u, expanding an unset variable is an error
+    pub pipefail: bool,  // a pipeline fails if any of its commands fails
+    pub xtrace: bool,    // set -x, print every command before running it
+}
+
+impl Options {
+    // all option names in the order `set -o` lists them
+    pub const NAMES: [&'static str; 5] = ["errexit", "noclobber", "nounset", "pipefail", "xtrace"];
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "errexit" => Some(self.errexit),
+            "noclobber" => Some(self.noclobber),
+            "nounset" => Some(self.nounset),
+            "pipefail" => Some(self.pipefail),
+            "xtrace" => Some(self.xtrace),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: bool) -> Result<(), String> {
+        match name {
+            "errexit" => self.errexit = value,
+            "noclobber" => self.noclobber = value,
+            "nounset" => self.nounset = value,
+            "pipefail" => self.pipefail = value,
+            "xtrace" => self.xtrace = value,
+            _ => return Err(format!("{name}: invalid option name")),
+        }
+        Ok(())
+    }
+
+    // the option of a single letter flag like `set -e`
+    pub fn from_flag(flag: char) -> Option<&'static str> {
+        match flag {
+            'C' => Some("noclobber"),
+            'e' => Some("errexit"),
+            'u' => Some("nounset"),
+            'x' => Some("xtrace"),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::io::{self, Read};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use std::time::{Duration, Instant};
+
+use nix::libc;
+
+// how `read` reads a line
+pub struct ReadOptions {
+    pub raw: bool,                 // -r, backslashes are kept
+    pub delimiter: u8,             // -d, a newline by default
+    pub count: Option<usize>,      // -n, stop after this many characters
+    pub timeout: Option<Duration>, // -t
+}
+
+// why reading stopped
+#[derive(Debug, PartialEq)]
+pub enum End {
+    Delimiter,
+    Count,
+    Eof,
+    Timeout,
+}
+
+// a character and whether it was escaped with a backslash, escaped characters never split fields
+pub type Char = (char, bool);
+
+// what reading a byte gave
+enum Next {
+    Byte(u8),
+    Eof,
+    Timeout,
+}
+
+// reads up to the delimiter one byte at a time, so nothing after it is consumed and later
+// commands still get it, the delimiter is not part of the line
+pub fn read_line(input: &mut (impl Read + AsFd), options: &ReadOptions) -> io::Result<(Vec<Char>, End)> {
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut line: Vec<Char> = Vec::new();
+    let mut escaped = false;
+
+    loop {
+        if options.count.is_some_and(|count| line.len() >= count) {
+            return Ok((line, End::Count));
+        }
+        let lead = match read_byte(input, deadline)? {
+            Next::Byte(byte) => byte,
+            Next::Eof => return Ok((line, End::Eof)),
+            Next::Timeout => return Ok((line, End::Timeout)),
+        };
+        if lead == options.delimiter && !escaped {
+            return Ok((line, End::Delimiter));
+        }
+
+        // the rest of a UTF-8 sequence, invalid input becomes U+FFFD
+        let mut bytes = vec![lead];
+        let len = match lead {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        while bytes.len() < len {
+            match read_byte(input, deadline)? {
+                Next::Byte(byte) => bytes.push(byte),
+                Next::Eof | Next::Timeout => break,
+            }
+        }
+
+        for c in String::from_utf8_lossy(&bytes).chars() {
+            match (c, escaped) {
+                // a backslash before a newline continues the line
+                ('\n', true) => escaped = false,
+                ('\\', false) if !options.raw => escaped = true,
+                _ => {
+                    line.push((c, escaped));
+                    escaped = false;
+                }
+            }
+        }
+    }
+}
+
+fn read_byte(input: &mut (impl Read + AsFd), deadline: Option<Instant>) -> io::Result<Next> {
+    if let Some(deadline) = deadline
+        && !wait_readable(input.as_fd(), deadline.saturating_duration_since(Instant::now()))?
+    {
+        return Ok(Next::Timeout);
+    }
+    let mut byte = [0];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(Next::Eof),
+            Ok(_) => return Ok(Next::Byte(byte[0])),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// true if the fd can be read without blocking before the timeout ends, also at end of input
+pub fn wait_readable(fd: BorrowedFd, timeout: Duration) -> io::Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // rounded up, so a timeout below a millisecond doesn't turn into a busy loop
+    let millis = timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+    loop {
+        match unsafe { libc::poll(&mut pollfd, 1, millis) } {
+            -1 => {
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+            ready => return Ok(ready > 0),
+        }
+    }
+}
+
+// turns off the echo of a terminal until it is dropped, for `read -s`
+pub struct EchoOff {
+    fd: i32,
+    saved: Option<libc::termios>,
+}
+
+impl EchoOff {
+    pub fn new(fd: BorrowedFd) -> Self {
+        let fd = fd.as_raw_fd();
+        // all fields are plain integers, zero is a valid value
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        // not a terminal, nothing is echoed anyway
+        if unsafe { libc::tcgetattr(fd, &mut termios) } == -1 {
+            return EchoOff { fd, saved: None };
+        }
+        let saved = termios;
+        termios.c_lflag &= !libc::ECHO;
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) };
+        EchoOff { fd, saved: Some(saved) }
+    }
+}
+
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        if let Some(saved) = &self.saved {
+            unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, saved) };
+        }
+    }
+}
+
+// splits a line with IFS like `read` does: IFS whitespace at the edges is trimmed, runs of it
+// delimit fields, every other IFS character delimits exactly one field, with `max` fields the
+// last one gets the rest of the line
+pub fn split_fields(line: &[Char], ifs: &str, max: Option<usize>) -> Vec<String> {
+    let is_ifs = |(c, escaped): &Char| !escaped && ifs.contains(*c);
+    let is_ifs_space = |ch: &Char| is_ifs(ch) && ch.0.is_whitespace();
+    let text = |chars: &[Char]| chars.iter().map(|(c, _)| *c).collect::<String>();
+
+    let mut fields: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < line.len() && is_ifs_space(&line[i]) {
+        i += 1;
+    }
+    while i < line.len() {
+        if max.is_some_and(|max| fields.len() + 1 >= max) {
+            let mut end = line.len();
+            while end > i && is_ifs_space(&line[end - 1]) {
+                end -= 1;
+            }
+            fields.push(text(&line[i..end]));
+            return fields;
+        }
+
+        let start = i;
+        while i < line.len() && !is_ifs(&line[i]) {
+            i += 1;
+        }
+        fields.push(text(&line[start..i]));
+
+        // the delimiter, IFS whitespace around at most one other IFS character
+        while i < line.len() && is_ifs_space(&line[i]) {
+            i += 1;
+        }
+        if i < line.len() && is_ifs(&line[i]) {
+            i += 1;
+            while i < line.len() && is_ifs_space(&line[i]) {
+                i += 1;
+            }
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Write;
+
+    use super::*;
+
+    fn chars(text: &str) -> Vec<Char> {
+        text.chars().map(|c| (c, false)).collect()
+    }
+
+    #[test]
+    fn test_split_fields() {
+        let ifs = " \t\n";
+        assert_eq!(split_fields(&chars("  a  b c  "), ifs, None), vec!["a", "b", "c"]);
+        assert_eq!(split_fields(&chars("  a  b c  "), ifs, Some(2)), vec!["a", "b c"]);
+        assert_eq!(split_fields(&chars(" a "), ifs, Some(3)), vec!["a"]);
+        assert_eq!(split_fields(&chars("a::b : c:"), ":", None), vec!["a", "", "b ", " c"]);
+        assert_eq!(split_fields(&chars("a::b : c:"), ": ", None), vec!["a", "", "b", "c"]);
+        assert_eq!(split_fields(&chars(" a b "), "", Some(2)), vec![" a b "]);
+        assert!(split_fields(&chars("   "), ifs, Some(1)).is_empty());
+
+        let line = vec![('a', false), (' ', true), ('b', false), (' ', false), ('c', false)];
+        assert_eq!(split_fields(&line, ifs, None), vec!["a b", "c"]);
+    }
+
+    #[test]
+    fn test_read_line() {
+        let (reader, mut writer) = io::pipe().unwrap();
+        let mut reader = File::from(std::os::fd::OwnedFd::from(reader));
+        writer.write_all("a\\ b\\\nc ü\nrest".as_bytes()).unwrap();
+        let mut options = ReadOptions {
+            raw: false,
+            delimiter: b'\n',
+            count: None,
+            timeout: Some(Duration::from_millis(20)),
+        };
+
+        let (line, end) = read_line(&mut reader, &options).unwrap();
+        assert_eq!(end, End::Delimiter);
+        assert_eq!(line, vec![('a', false), (' ', true), ('b', false), ('c', false), (' ', false), ('ü', false)]);
+
+        // nothing after the delimiter was consumed
+        options.count = Some(2);
+        let (line, end) = read_line(&mut reader, &options).unwrap();
+        assert_eq!((line, end), (chars("re"), End::Count));
+
+        options.count = None;
+        let (line, end) = read_line(&mut reader, &options).unwrap();
+        assert_eq!((line, end), (chars("st"), End::Timeout));
+
+        writer.write_all(b"\\").unwrap();
+        drop(writer);
+        options.raw = true;
+        let (line, end) = read_line(&mut reader, &options).unwrap();
+        assert_eq!((line, end), (chars("\\"), End::Eof));
+    }
+}
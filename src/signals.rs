@@ -0,0 +1,183 @@
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+
+use nix::libc;
+use nix::sys::signal::{self, SigHandler, Signal};
+use nix::sys::wait::{WaitPidFlag, waitpid};
+use nix::unistd::Pid;
+
+// signals the interactive shell ignores, children get the default disposition back
+const IGNORED: [Signal; 3] = [Signal::SIGINT, Signal::SIGQUIT, Signal::SIGTTOU];
+
+// pids of the children that were not waited for or reaped yet, SIGHUP is forwarded to them
+const MAX_CHILDREN: usize = 64;
+static CHILDREN: [AtomicI32; MAX_CHILDREN] = [const { AtomicI32::new(0) }; MAX_CHILDREN];
+// set by SIGCHLD, a child exited and may need reaping
+static CHILD_EXITED: AtomicBool = AtomicBool::new(false);
+static HANGUP: AtomicBool = AtomicBool::new(false);
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+// signals with a trap, bit N is signal N, trapped signals that arrived are pending until
+// the shell runs their traps at a safe point
+static CAUGHT: AtomicU64 = AtomicU64::new(0);
+static TRAP_IGNORED: AtomicU64 = AtomicU64::new(0);
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+// sets up the signal dispositions of the shell, only an interactive shell ignores Ctrl-C
+pub fn init(interactive: bool) {
+    // an inherited ignored SIGCHLD would make the kernel discard the exit statuses of children,
+    // the handler only records the exit, children are reaped at the next safe point
+    set(Signal::SIGCHLD, SigHandler::Handler(on_child));
+    INTERACTIVE.store(interactive, Ordering::SeqCst);
+    if !interactive {
+        return;
+    }
+    for sig in IGNORED {
+        set(sig, SigHandler::SigIgn);
+    }
+    set(Signal::SIGHUP, SigHandler::Handler(on_hangup));
+}
+
+// resets the dispositions changed by init, runs in a child between fork and exec so it
+// must only do async-signal-safe calls
+pub fn reset_for_child() {
+    set(Signal::SIGCHLD, SigHandler::SigDfl);
+    // signals ignored with `trap ''` stay ignored in children
+    let trap_ignored = TRAP_IGNORED.load(Ordering::SeqCst);
+    for sig in IGNORED.into_iter().chain([Signal::SIGHUP]) {
+        if trap_ignored & bit(sig) == 0 {
+            set(sig, SigHandler::SigDfl);
+        }
+    }
+    let caught = CAUGHT.load(Ordering::SeqCst);
+    for sig in Signal::iterator() {
+        if caught & bit(sig) != 0 {
+            set(sig, SigHandler::SigDfl);
+        }
+    }
+}
+
+// `trap 'cmd' SIG`, the signal is only recorded, the shell runs the trap later
+pub fn catch(sig: Signal) {
+    TRAP_IGNORED.fetch_and(!bit(sig), Ordering::SeqCst);
+    CAUGHT.fetch_or(bit(sig), Ordering::SeqCst);
+    set(sig, SigHandler::Handler(on_trapped));
+}
+
+// `trap '' SIG`
+pub fn ignore(sig: Signal) {
+    CAUGHT.fetch_and(!bit(sig), Ordering::SeqCst);
+    TRAP_IGNORED.fetch_or(bit(sig), Ordering::SeqCst);
+    set(sig, SigHandler::SigIgn);
+}
+
+// `trap - SIG`, back to the disposition set by init
+pub fn restore(sig: Signal) {
+    CAUGHT.fetch_and(!bit(sig), Ordering::SeqCst);
+    TRAP_IGNORED.fetch_and(!bit(sig), Ordering::SeqCst);
+    let handler = match INTERACTIVE.load(Ordering::SeqCst) {
+        true if sig == Signal::SIGHUP => SigHandler::Handler(on_hangup),
+        true if IGNORED.contains(&sig) => SigHandler::SigIgn,
+        _ => SigHandler::SigDfl,
+    };
+    set(sig, handler);
+}
+
+// the trapped signals that arrived since the last call, in order of their number
+pub fn take_pending() -> Vec<Signal> {
+    let pending = PENDING.swap(0, Ordering::SeqCst);
+    Signal::iterator().filter(|sig| pending & bit(*sig) != 0).collect()
+}
+
+fn bit(sig: Signal) -> u64 {
+    1 << (sig as u32 % 64)
+}
+
+fn set(sig: Signal, handler: SigHandler) {
+    // fesh is single-threaded!
+    let _ = unsafe { signal::signal(sig, handler) };
+}
+
+extern "C" fn on_trapped(sig: libc::c_int) {
+    if let Ok(sig) = Signal::try_from(sig) {
+        PENDING.fetch_or(bit(sig), Ordering::SeqCst);
+    }
+}
+
+extern "C" fn on_child(_: libc::c_int) {
+    CHILD_EXITED.store(true, Ordering::SeqCst);
+}
+
+extern "C" fn on_hangup(_: libc::c_int) {
+    HANGUP.store(true, Ordering::SeqCst);
+    for pid in &CHILDREN {
+        let pid = pid.load(Ordering::SeqCst);
+        if pid > 0 {
+            unsafe { libc::kill(pid, libc::SIGHUP) };
+        }
+    }
+}
+
+// true once the terminal hung up, the shell should exit
+pub fn hangup_received() -> bool {
+    HANGUP.load(Ordering::SeqCst)
+}
+
+pub fn add_child(pid: u32) {
+    let Ok(pid) = i32::try_from(pid) else {
+        return;
+    };
+    if let Some(slot) = CHILDREN.iter().find(|slot| slot.load(Ordering::SeqCst) == 0) {
+        slot.store(pid, Ordering::SeqCst);
+    }
+}
+
+// a child that was waited for or reaped, its pid may be reused
+pub fn remove_child(pid: i32) {
+    if let Some(slot) = CHILDREN.iter().find(|slot| slot.load(Ordering::SeqCst) == pid) {
+        slot.store(0, Ordering::SeqCst);
+    }
+}
+
+// reaps children that exited without being waited for, so they don't stay zombies, the
+// shell calls it at every safe point when no pipeline is running
+pub fn reap_children() {
+    if !CHILD_EXITED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    while let Ok(status) = waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+        match status.pid() {
+            Some(pid) => remove_child(pid.as_raw()),
+            None => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_reap_children() {
+        // spawned without a wait, like a child the shell lost track of
+        let pid = Command::new("true").spawn().unwrap().id();
+        add_child(pid);
+        let tracked = || CHILDREN.iter().any(|slot| slot.load(Ordering::SeqCst) == pid as i32);
+        assert!(tracked());
+
+        for _ in 0..100 {
+            CHILD_EXITED.store(true, Ordering::SeqCst);
+            reap_children();
+            if !tracked() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!tracked());
+        // the zombie is gone as well
+        assert!(waitpid(Pid::from_raw(pid as i32), Some(WaitPidFlag::WNOHANG)).is_err());
+    }
+}
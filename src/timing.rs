@@ -0,0 +1,132 @@
+use std::io;
+use std::time::Duration;
+
+use nix::libc;
+
+// the output of `time` if TIMEFORMAT is not set, like bash
+pub const DEFAULT_FORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+// the output of `time -p`
+pub const POSIX_FORMAT: &str = "real %2R\nuser %2U\nsys %2S";
+
+// cpu times and peak memory of processes
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct Usage {
+    pub user: Duration,
+    pub sys: Duration,
+    pub max_rss: u64, // KiB
+}
+
+impl Usage {
+    pub fn from_rusage(rusage: &libc::rusage) -> Self {
+        Usage {
+            user: Self::duration(rusage.ru_utime),
+            sys: Self::duration(rusage.ru_stime),
+            max_rss: rusage.ru_maxrss.max(0) as u64,
+        }
+    }
+
+    // the usage of fesh itself, which includes builtins run in the shell
+    pub fn of_shell() -> io::Result<Self> {
+        // all fields are plain integers, zero is a valid value
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut rusage) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self::from_rusage(&rusage))
+    }
+
+    // adds the times of another process, the peak memory is the larger one
+    pub fn add(&mut self, other: Usage) {
+        self.user += other.user;
+        self.sys += other.sys;
+        self.max_rss = self.max_rss.max(other.max_rss);
+    }
+
+    // the times spent since an earlier usage of the same process
+    pub fn since(&self, earlier: Usage) -> Usage {
+        Usage {
+            user: self.user.saturating_sub(earlier.user),
+            sys: self.sys.saturating_sub(earlier.sys),
+            max_rss: self.max_rss,
+        }
+    }
+
+    fn duration(time: libc::timeval) -> Duration {
+        Duration::new(time.tv_sec.max(0) as u64, (time.tv_usec.clamp(0, 999_999) * 1000) as u32)
+    }
+}
+
+// formats the times like bash's TIMEFORMAT: `%[p][l]R`, `%[p][l]U` and `%[p][l]S` are the real,
+// user and system time with p (0-3) decimals, `l` adds minutes, `%P` is the cpu percentage,
+// `%M` the peak memory of the commands in KiB and `%%` a literal %
+pub fn format(format: &str, real: Duration, usage: &Usage) -> String {
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        let precision = chars.next_if(char::is_ascii_digit).and_then(|d| d.to_digit(10)).map(|d| d.min(3) as usize);
+        let long = chars.next_if_eq(&'l').is_some();
+        let time = match chars.next() {
+            Some('R') => real,
+            Some('U') => usage.user,
+            Some('S') => usage.sys,
+            Some('P') if precision.is_none() && !long => {
+                let cpu = (usage.user + usage.sys).as_secs_f64();
+                let percent = if real.is_zero() { 0.0 } else { cpu / real.as_secs_f64() * 100.0 };
+                output.push_str(&format!("{:.2}", percent));
+                continue;
+            }
+            Some('M') if precision.is_none() && !long => {
+                output.push_str(&usage.max_rss.to_string());
+                continue;
+            }
+            Some('%') if precision.is_none() && !long => {
+                output.push('%');
+                continue;
+            }
+            // not a known conversion, kept as written
+            other => {
+                output.push('%');
+                output.extend(precision.map(|p| char::from(b'0' + p as u8)));
+                if long {
+                    output.push('l');
+                }
+                output.extend(other);
+                continue;
+            }
+        };
+        output.push_str(&format_duration(time, precision.unwrap_or(3), long));
+    }
+    output
+}
+
+fn format_duration(time: Duration, precision: usize, long: bool) -> String {
+    let seconds = time.as_secs_f64();
+    if !long {
+        return format!("{:.*}", precision, seconds);
+    }
+    let minutes = (seconds / 60.0).floor();
+    format!("{}m{:.*}s", minutes, precision, seconds - minutes * 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let usage = Usage {
+            user: Duration::from_millis(1500),
+            sys: Duration::from_millis(500),
+            max_rss: 2048,
+        };
+        let real = Duration::from_millis(64_250);
+        assert_eq!(format(DEFAULT_FORMAT, real, &usage), "\nreal\t1m4.250s\nuser\t0m1.500s\nsys\t0m0.500s");
+        assert_eq!(format(POSIX_FORMAT, real, &usage), "real 64.25\nuser 1.50\nsys 0.50");
+        assert_eq!(format("%0R %P%% %MKiB %x %5lU", real, &usage), "64 3.11% 2048KiB %x 0m1.500s");
+    }
+}
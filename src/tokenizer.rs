@@ -0,0 +1,357 @@
+use crate::command::command_error::CommandError;
+use crate::command::operator::Operator;
+use crate::command::span::Span;
+use crate::command::token::Token;
+use crate::command::word::{Parameter, Word, WordPart};
+use crate::logger;
+
+pub struct Tokenizer {
+    pub logger: logger::Logger,
+}
+
+// a token with its start and end offset in characters
+type OffsetToken = (Token, usize, usize);
+
+// collects the parts of the word currently being read
+struct WordBuilder {
+    parts: Vec<WordPart>,
+    started: bool,
+    start: usize,
+}
+
+impl WordBuilder {
+    fn new() -> Self {
+        WordBuilder {
+            parts: Vec::new(),
+            started: false,
+            start: 0,
+        }
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.started = true;
+        if let Some(WordPart::Literal(s)) = self.parts.last_mut() {
+            s.push(c);
+        } else {
+            self.parts.push(WordPart::Literal(c.to_string()));
+        }
+    }
+
+    fn push_part(&mut self, part: WordPart) {
+        self.started = true;
+        self.parts.push(part);
+    }
+
+    // takes the word as the fd of a redirect if it is only digits, like the 2 in `2>`
+    fn take_io_number(&mut self) -> Option<u32> {
+        let fd = match self.parts.as_slice() {
+            [WordPart::Literal(s)] if s.chars().all(|c| c.is_ascii_digit()) => s.parse().ok()?,
+            _ => return None,
+        };
+        self.parts.clear();
+        self.started = false;
+        Some(fd)
+    }
+
+    fn finish(&mut self, tokens: &mut Vec<OffsetToken>, end: usize) {
+        if self.started {
+            let word = Word::new(std::mem::take(&mut self.parts));
+            tokens.push((Token::Word(word), self.start, end));
+            self.started = false;
+        }
+    }
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Tokenizer {
+            logger: logger::Logger::new(false),
+        }
+    }
+
+    pub fn tokenize(&self, input: &str) -> Result<Vec<Token>, CommandError> {
+        let chars: Vec<char> = input.chars().collect();
+        let (tokens, _) = Self::tokenize_from(&chars, 0, false)?;
+        let tokens: Vec<Token> = tokens.into_iter().map(|(token, _, _)| token).collect();
+
+        self.logger
+            .print_debug(String::from("Tokenizer"), format!("tokens: {:?}", tokens));
+        Ok(tokens)
+    }
+
+    // like tokenize, but also returns where in the input each token is
+    pub fn tokenize_spanned(&self, input: &str) -> Result<Vec<(Token, Span)>, CommandError> {
+        let chars: Vec<char> = input.chars().collect();
+        let (tokens, _) = Self::tokenize_from(&chars, 0, false)?;
+
+        let mut spanned = Vec::new();
+        let (mut line, mut column, mut offset) = (1, 1, 0);
+        for (token, start, end) in tokens {
+            while offset < start {
+                if chars[offset] == '\n' {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+                offset += 1;
+            }
+            spanned.push((token, Span { start, end, line, column }));
+        }
+        Ok(spanned)
+    }
+
+    // tokenizes until the end of input, or until the closing `)` of an array literal
+    fn tokenize_from(chars: &[char], start: usize, in_array: bool) -> Result<(Vec<OffsetToken>, usize), CommandError> {
+        let mut tokens: Vec<OffsetToken> = Vec::new();
+        let mut word = WordBuilder::new();
+        let mut i = start;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if !word.started {
+                word.start = i;
+            }
+            match c {
+                ' ' | '\t' => {
+                    word.finish(&mut tokens, i);
+                    i += 1;
+                }
+                '\n' => {
+                    word.finish(&mut tokens, i);
+                    if !in_array {
+                        tokens.push((Token::Newline, i, i + 1));
+                    }
+                    i += 1;
+                }
+                // `a; b` runs like two lines
+                ';' if !in_array => {
+                    word.finish(&mut tokens, i);
+                    tokens.push((Token::Newline, i, i + 1));
+                    i += 1;
+                }
+                '#' if !word.started => {
+                    let end = chars[i..].iter().position(|&c| c == '\n').map(|end| i + end).unwrap_or(chars.len());
+                    // comments inside array literals are dropped
+                    if !in_array {
+                        tokens.push((Token::Comment(chars[i + 1..end].iter().collect()), i, end));
+                    }
+                    i = end;
+                }
+                ')' if in_array => {
+                    word.finish(&mut tokens, i);
+                    return Ok((tokens, i + 1));
+                }
+                '|' | '>' | '<' | ';' if in_array => {
+                    return Err(CommandError::UnexpectedToken(c.to_string()));
+                }
+                '|' => {
+                    word.finish(&mut tokens, i);
+                    tokens.push((Token::Operator(Operator::Pipe), i, i + 1));
+                    i += 1;
+                }
+                '>' | '<' => {
+                    match word.take_io_number() {
+                        Some(fd) => tokens.push((Token::IoNumber(fd), word.start, i)),
+                        None => word.finish(&mut tokens, i),
+                    }
+                    let (operator, len) = match (c, chars.get(i + 1)) {
+                        ('>', Some('>')) => (Operator::RedirectAppend, 2),
+                        ('>', Some('&')) => (Operator::RedirectDuplicate, 2),
+                        ('>', Some('|')) => (Operator::RedirectClobber, 2),
+                        ('>', _) => (Operator::RedirectOverwrite, 1),
+                        _ => (Operator::RedirectInput, 1),
+                    };
+                    tokens.push((Token::Operator(operator), i, i + len));
+                    i += len;
+                }
+                '(' if !in_array && Self::is_assignment_prefix(&word.parts) => {
+                    let (array_tokens, next) = Self::tokenize_from(chars, i + 1, true)?;
+                    let elements = array_tokens
+                        .into_iter()
+                        .filter_map(|(t, _, _)| match t {
+                            Token::Word(w) => Some(w),
+                            Token::Operator(_) | Token::IoNumber(_) | Token::Newline | Token::Comment(_) => None,
+                        })
+                        .collect();
+                    word.push_part(WordPart::Array(elements));
+                    i = next;
+                }
+                '\'' => {
+                    let end = match chars[i + 1..].iter().position(|&c| c == '\'') {
+                        Some(end) => i + 1 + end,
+                        None => return Err(CommandError::UnterminatedQuote('\'')),
+                    };
+                    word.push_part(WordPart::SingleQuoted(chars[i + 1..end].iter().collect()));
+                    i = end + 1;
+                }
+                '"' => {
+                    let (parts, next) = Self::read_double_quoted(chars, i + 1)?;
+                    word.push_part(WordPart::DoubleQuoted(parts));
+                    i = next;
+                }
+                '\\' => {
+                    match chars.get(i + 1) {
+                        // line continuation
+                        Some('\n') => {}
+                        Some(&escaped) => word.push_part(WordPart::Escaped(escaped)),
+                        // a trailing backslash is kept as a literal one
+                        None => word.push_part(WordPart::Escaped('\\')),
+                    }
+                    i += 2;
+                }
+                '$' => match Self::read_parameter(chars, Self::skip_continuations(chars, i + 1))? {
+                    Some((part, next)) => {
+                        word.push_part(part);
+                        i = next;
+                    }
+                    None => {
+                        word.push_char('$');
+                        i = Self::skip_continuations(chars, i + 1);
+                    }
+                },
+                '~' if !word.started && Self::ends_tilde_prefix(chars.get(i + 1)) => {
+                    word.push_part(WordPart::Tilde);
+                    i += 1;
+                }
+                _ => {
+                    word.push_char(c);
+                    i += 1;
+                }
+            }
+        }
+
+        if in_array {
+            return Err(CommandError::UnterminatedQuote(')'));
+        }
+        word.finish(&mut tokens, i);
+        Ok((tokens, i))
+    }
+
+    // true if the word read so far is `name=`, `name+=` or `name[sub]=`
+    fn is_assignment_prefix(parts: &[WordPart]) -> bool {
+        let text = match parts {
+            [WordPart::Literal(text)] => text,
+            _ => return false,
+        };
+        let name = match text.strip_suffix("+=").or_else(|| text.strip_suffix('=')) {
+            Some(name) => name,
+            None => return false,
+        };
+        let name = match name.split_once('[') {
+            Some((name, subscript)) if subscript.ends_with(']') => name,
+            Some(_) => return false,
+            None => name,
+        };
+        Self::is_name(name)
+    }
+
+    pub fn is_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+                chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            _ => false,
+        }
+    }
+
+    fn ends_tilde_prefix(next: Option<&char>) -> bool {
+        matches!(next, None | Some('/') | Some(' ') | Some('\t') | Some('\n') | Some(';') | Some('|') | Some('>') | Some('<'))
+    }
+
+    // reads the body of a double quoted string starting after the opening quote,
+    // returns the parts and the index after the closing quote
+    fn read_double_quoted(chars: &[char], start: usize) -> Result<(Vec<WordPart>, usize), CommandError> {
+        let mut parts: Vec<WordPart> = Vec::new();
+        let mut literal = String::new();
+        let mut i = start;
+
+        let flush = |literal: &mut String, parts: &mut Vec<WordPart>| {
+            if !literal.is_empty() {
+                parts.push(WordPart::Literal(std::mem::take(literal)));
+            }
+        };
+
+        while i < chars.len() {
+            match chars[i] {
+                '"' => {
+                    flush(&mut literal, &mut parts);
+                    return Ok((parts, i + 1));
+                }
+                // inside double quotes a backslash only escapes these characters
+                '\\' if matches!(chars.get(i + 1), Some('$') | Some('"') | Some('\\') | Some('`')) => {
+                    flush(&mut literal, &mut parts);
+                    parts.push(WordPart::Escaped(chars[i + 1]));
+                    i += 2;
+                }
+                '\\' if chars.get(i + 1) == Some(&'\n') => {
+                    i += 2;
+                }
+                '$' => match Self::read_parameter(chars, Self::skip_continuations(chars, i + 1))? {
+                    Some((part, next)) => {
+                        flush(&mut literal, &mut parts);
+                        parts.push(part);
+                        i = next;
+                    }
+                    None => {
+                        literal.push('$');
+                        i = Self::skip_continuations(chars, i + 1);
+                    }
+                },
+                c => {
+                    literal.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        Err(CommandError::UnterminatedQuote('"'))
+    }
+
+    // returns the index after any line continuations at `start`, so `$\<newline>x` is `$x`
+    fn skip_continuations(chars: &[char], start: usize) -> usize {
+        let mut i = start;
+        while chars.get(i) == Some(&'\\') && chars.get(i + 1) == Some(&'\n') {
+            i += 2;
+        }
+        i
+    }
+
+    // reads a parameter after a `$`, returns None if the `$` is a plain literal
+    fn read_parameter(chars: &[char], start: usize) -> Result<Option<(WordPart, usize)>, CommandError> {
+        match chars.get(start) {
+            Some('{') => {
+                let end = match chars[start + 1..].iter().position(|&c| c == '}') {
+                    Some(end) => start + 1 + end,
+                    None => return Err(CommandError::UnterminatedQuote('}')),
+                };
+                let body: String = chars[start + 1..end].iter().collect();
+                match Parameter::parse(&body) {
+                    Some(parameter) => Ok(Some((WordPart::Parameter(parameter), end + 1))),
+                    None => Err(CommandError::BadSubstitution(format!("${{{}}}", body))),
+                }
+            }
+            Some(c) if c.is_ascii_alphabetic() || *c == '_' => {
+                let end = chars[start..]
+                    .iter()
+                    .position(|c| !c.is_ascii_alphanumeric() && *c != '_')
+                    .map(|end| start + end)
+                    .unwrap_or(chars.len());
+                let name: String = chars[start..end].iter().collect();
+                Ok(Some((WordPart::Parameter(Parameter::new(&name)), end)))
+            }
+            // single character parameters: $0..$9, $@, $*, $#, $?
+            Some(c) if c.is_ascii_digit() || matches!(c, '@' | '*' | '#' | '?') => {
+                Ok(Some((WordPart::Parameter(Parameter::new(&c.to_string())), start + 1)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use nix::sys::signal::Signal;
+
+use crate::util;
+
+// what a trap is set on, a signal or one of the pseudo-signals
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Condition {
+    Exit,  // the shell exits
+    Err,   // a pipeline fails
+    Debug, // before every pipeline
+    Signal(Signal),
+}
+
+impl Condition {
+    // parses a condition like `EXIT`, `0`, `INT`, `SIGINT`, `int` or `2`
+    pub fn parse(text: &str) -> Result<Condition, String> {
+        let name = text.to_uppercase();
+        let condition = match name.as_str() {
+            "EXIT" | "0" => Condition::Exit,
+            "ERR" => Condition::Err,
+            "DEBUG" => Condition::Debug,
+            _ => {
+                let signal = match name.parse::<i32>() {
+                    Ok(number) => Signal::try_from(number).ok(),
+                    Err(_) => Signal::from_str(&name)
+                        .or_else(|_| Signal::from_str(&format!("SIG{name}")))
+                        .ok(),
+                };
+                Condition::Signal(signal.ok_or_else(|| format!("{text}: invalid signal specification"))?)
+            }
+        };
+        if let Condition::Signal(Signal::SIGKILL | Signal::SIGSTOP) = condition {
+            return Err(format!("{text}: signal can't be trapped"));
+        }
+        Ok(condition)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Condition::Exit => "EXIT",
+            Condition::Err => "ERR",
+            Condition::Debug => "DEBUG",
+            Condition::Signal(signal) => signal.as_str(),
+        }
+    }
+
+    // the order `trap` lists conditions in, EXIT first and the signals by number
+    fn order(&self) -> i32 {
+        match self {
+            Condition::Exit => 0,
+            Condition::Signal(signal) => *signal as i32,
+            Condition::Debug => i32::MAX - 1,
+            Condition::Err => i32::MAX,
+        }
+    }
+}
+
+// the commands set with `trap`, an empty command means the signal is ignored
+pub struct Traps {
+    actions: HashMap<Condition, String>,
+}
+
+impl Default for Traps {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Traps {
+    pub fn new() -> Self {
+        Traps { actions: HashMap::new() }
+    }
+
+    pub fn get(&self, condition: Condition) -> Option<&str> {
+        self.actions.get(&condition).map(|action| action.as_str())
+    }
+
+    pub fn set(&mut self, condition: Condition, action: String) {
+        self.actions.insert(condition, action);
+    }
+
+    pub fn reset(&mut self, condition: Condition) {
+        self.actions.remove(&condition);
+    }
+
+    // removes the EXIT trap so it only runs once
+    pub fn take_exit(&mut self) -> Option<String> {
+        self.actions.remove(&Condition::Exit)
+    }
+
+    // a forked builtin doesn't run the traps of the shell, ignored signals stay ignored
+    pub fn clear_for_subshell(&mut self) {
+        self.actions.retain(|_, action| action.is_empty());
+    }
+
+    // the `trap` commands that set the given traps again, all traps if none are given
+    pub fn commands(&self, conditions: &[Condition]) -> Vec<String> {
+        let mut entries: Vec<(&Condition, &String)> = self
+            .actions
+            .iter()
+            .filter(|(condition, _)| conditions.is_empty() || conditions.contains(condition))
+            .collect();
+        entries.sort_by_key(|(condition, _)| condition.order());
+        entries
+            .iter()
+            .map(|(condition, action)| format!("trap -- {} {}", util::quote(action), condition.name()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_condition() {
+        assert_eq!(Condition::parse("EXIT"), Ok(Condition::Exit));
+        assert_eq!(Condition::parse("0"), Ok(Condition::Exit));
+        assert_eq!(Condition::parse("err"), Ok(Condition::Err));
+        assert_eq!(Condition::parse("INT"), Ok(Condition::Signal(Signal::SIGINT)));
+        assert_eq!(Condition::parse("sigterm"), Ok(Condition::Signal(Signal::SIGTERM)));
+        assert_eq!(Condition::parse("1"), Ok(Condition::Signal(Signal::SIGHUP)));
+        assert!(Condition::parse("FOO").is_err());
+        assert!(Condition::parse("KILL").is_err());
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut traps = Traps::new();
+        traps.set(Condition::Err, String::from("echo failed"));
+        traps.set(Condition::Signal(Signal::SIGTERM), String::new());
+        traps.set(Condition::Exit, String::from("rm -f $tmp"));
+        assert_eq!(
+            traps.commands(&[]),
+            vec!["trap -- 'rm -f $tmp' EXIT", "trap -- '' SIGTERM", "trap -- 'echo failed' ERR"]
+        );
+        assert_eq!(traps.commands(&[Condition::Err]), vec!["trap -- 'echo failed' ERR"]);
+
+        traps.clear_for_subshell();
+        assert_eq!(traps.commands(&[]), vec!["trap -- '' SIGTERM"]);
+    }
+}
@@ -1,4 +1,105 @@
+use std::env;
+use std::ffi::OsStr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
 pub fn exit_with_error(error: &str) -> ! {
     eprintln!("error: {}", error);
     std::process::exit(1);
-}
\ No newline at end of file
+}
+
+// quotes a string so the shell reads it back as exactly one word with the same text
+pub fn quote(s: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:,+@%^".contains(c);
+    if !s.is_empty() && s.chars().all(is_safe) {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+// true if the path is a regular file with any execute bit set
+pub fn is_executable(path: &Path) -> bool {
+    match path.metadata() {
+        Ok(metadata) => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+// searches the directories in PATH for an executable with the given name
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    find_in(&env::var_os("PATH")?, name)
+}
+
+// searches the directories of a PATH value for an executable with the given name
+pub fn find_in(path: &OsStr, name: &str) -> Option<PathBuf> {
+    env::split_paths(path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+// every executable with the given name in the directories of PATH, in the order of PATH
+pub fn find_all_in_path(name: &str) -> Vec<PathBuf> {
+    let Some(path) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .filter(|candidate| is_executable(candidate))
+        .collect()
+}
+
+// the names of all executables in the directories of PATH
+pub fn executables_in_path() -> Vec<String> {
+    let Some(path) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = env::split_paths(&path)
+        .filter_map(|dir| dir.read_dir().ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// the number of inserted, deleted or replaced characters and swapped neighbours that turn
+// a into b (optimal string alignment distance), `gti` is one swap away from `git`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows[i][j] is the distance between the first i chars of a and the first j chars of b
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("git", "git"), 0);
+        assert_eq!(edit_distance("sl", "ls"), 1);
+        assert_eq!(edit_distance("grpe", "grep"), 1);
+        assert_eq!(edit_distance("cargo", "crago"), 1);
+        assert_eq!(edit_distance("", "cd"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
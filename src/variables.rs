@@ -0,0 +1,262 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::ffi::OsString;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Scalar(String),
+    Indexed(BTreeMap<usize, String>),
+    Associative(BTreeMap<String, String>),
+}
+
+impl Value {
+    // all element values in index or key order
+    pub fn values(&self) -> Vec<String> {
+        match self {
+            Value::Scalar(s) => vec![s.clone()],
+            Value::Indexed(map) => map.values().cloned().collect(),
+            Value::Associative(map) => map.values().cloned().collect(),
+        }
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        match self {
+            Value::Scalar(_) => vec![String::from("0")],
+            Value::Indexed(map) => map.keys().map(|k| k.to_string()).collect(),
+            Value::Associative(map) => map.keys().cloned().collect(),
+        }
+    }
+}
+
+// a variable as it was before a temporary assignment like in `IFS=: read`
+pub struct Saved {
+    value: Option<Value>,
+    exported: Option<OsString>,
+    pending_export: bool,
+}
+
+// the shell variable table, variables stored here are not exported to child processes,
+// exported variables live in the process environment
+pub struct Variables {
+    vars: HashMap<String, Value>,
+    // names given to `export` before they were set, they are exported once assigned
+    pending_exports: BTreeSet<String>,
+    pub positional_params: Vec<String>,
+    pub last_status: i32,
+}
+
+impl Default for Variables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Variables {
+            vars: HashMap::new(),
+            pending_exports: BTreeSet::new(),
+            positional_params: Vec::new(),
+            last_status: 0,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.vars.get(name)
+    }
+
+    // the scalar value of a variable, arrays yield their element 0
+    pub fn get_scalar(&self, name: &str) -> Option<String> {
+        match self.vars.get(name) {
+            Some(Value::Scalar(s)) => Some(s.clone()),
+            Some(Value::Indexed(map)) => map.get(&0).cloned(),
+            Some(Value::Associative(map)) => map.get("0").cloned(),
+            None => env::var(name).ok(),
+        }
+    }
+
+    pub fn is_associative(&self, name: &str) -> bool {
+        matches!(self.vars.get(name), Some(Value::Associative(_)))
+    }
+
+    pub fn set_scalar(&mut self, name: &str, value: String) {
+        match self.vars.get_mut(name) {
+            Some(Value::Indexed(map)) => {
+                map.insert(0, value);
+            }
+            Some(Value::Associative(map)) => {
+                map.insert(String::from("0"), value);
+            }
+            Some(scalar) => *scalar = Value::Scalar(value),
+            // exported variables stay exported
+            None if env::var_os(name).is_some() || self.pending_exports.remove(name) => {
+                // fesh is single-threaded!
+                unsafe { env::set_var(name, value) };
+            }
+            None => {
+                self.vars.insert(name.to_string(), Value::Scalar(value));
+            }
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: Value) {
+        if env::var_os(name).is_some() {
+            // arrays can't be exported
+            unsafe { env::remove_var(name) };
+        }
+        self.vars.insert(name.to_string(), value);
+    }
+
+    // sets a single element, turning a scalar into an indexed array
+    pub fn set_element(&mut self, name: &str, key: &str, value: String) -> Result<(), String> {
+        match self.vars.get_mut(name) {
+            Some(Value::Associative(map)) => {
+                map.insert(key.to_string(), value);
+            }
+            Some(Value::Indexed(map)) => {
+                let index = Self::resolve_index(map, key)?;
+                map.insert(index, value);
+            }
+            _ => {
+                let mut map = self.indexed(name);
+                let index = Self::resolve_index(&map, key)?;
+                map.insert(index, value);
+                self.set(name, Value::Indexed(map));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_element(&self, name: &str, key: &str) -> Option<String> {
+        match self.vars.get(name) {
+            Some(Value::Associative(map)) => map.get(key).cloned(),
+            _ => {
+                let map = self.indexed(name);
+                map.get(&Self::resolve_index(&map, key).ok()?).cloned()
+            }
+        }
+    }
+
+    // appends elements after the highest index of an indexed array
+    pub fn append_elements(&mut self, name: &str, values: Vec<String>) {
+        let mut map = self.indexed(name);
+        let next = map.keys().next_back().map(|k| k + 1).unwrap_or(0);
+        map.extend((next..).zip(values));
+        self.set(name, Value::Indexed(map));
+    }
+
+    // the variable as indexed array, a scalar becomes element 0
+    fn indexed(&self, name: &str) -> BTreeMap<usize, String> {
+        match self.vars.get(name) {
+            Some(Value::Indexed(map)) => map.clone(),
+            Some(Value::Associative(_)) => BTreeMap::new(),
+            _ => match self.get_scalar(name) {
+                Some(s) => BTreeMap::from([(0, s)]),
+                None => BTreeMap::new(),
+            },
+        }
+    }
+
+    // moves a shell variable to the process environment, so child processes see it
+    pub fn export(&mut self, name: &str, value: Option<String>) -> Result<(), String> {
+        let value = match (self.vars.get(name), value) {
+            (Some(Value::Indexed(_) | Value::Associative(_)), _) => {
+                return Err(format!("{}: arrays can't be exported", name));
+            }
+            (Some(Value::Scalar(s)), None) => s.clone(),
+            (_, Some(value)) => value,
+            (None, None) => {
+                // `export name` before `name=value`
+                if env::var_os(name).is_none() {
+                    self.pending_exports.insert(name.to_string());
+                }
+                return Ok(());
+            }
+        };
+        self.vars.remove(name);
+        // fesh is single-threaded!
+        unsafe { env::set_var(name, value) };
+        Ok(())
+    }
+
+    pub fn unset(&mut self, name: &str) {
+        self.vars.remove(name);
+        self.pending_exports.remove(name);
+        // fesh is single-threaded!
+        unsafe { env::remove_var(name) };
+    }
+
+    pub fn save(&self, name: &str) -> Saved {
+        Saved {
+            value: self.vars.get(name).cloned(),
+            exported: env::var_os(name),
+            pending_export: self.pending_exports.contains(name),
+        }
+    }
+
+    // puts a saved variable back, also if it was exported or unset in the meantime
+    pub fn restore(&mut self, name: &str, saved: Saved) {
+        match saved.value {
+            Some(value) => self.vars.insert(name.to_string(), value),
+            None => self.vars.remove(name),
+        };
+        if saved.pending_export {
+            self.pending_exports.insert(name.to_string());
+        } else {
+            self.pending_exports.remove(name);
+        }
+        // a variable that is not exported before or after never touches the environment
+        if saved.exported.is_none() && env::var_os(name).is_none() {
+            return;
+        }
+        // fesh is single-threaded!
+        match saved.exported {
+            Some(value) => unsafe { env::set_var(name, value) },
+            None => unsafe { env::remove_var(name) },
+        }
+    }
+
+    // removes a single element, element 0 of a scalar is the scalar itself
+    pub fn unset_element(&mut self, name: &str, key: &str) -> Result<(), String> {
+        match self.vars.get_mut(name) {
+            Some(Value::Associative(map)) => {
+                map.remove(key);
+            }
+            Some(Value::Indexed(map)) => {
+                let index = Self::resolve_index(map, key)?;
+                map.remove(&index);
+            }
+            _ => {
+                let map = self.indexed(name);
+                if !map.is_empty() && Self::resolve_index(&map, key)? == 0 {
+                    self.unset(name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the names marked for export that have no value yet
+    pub fn pending_exports(&self) -> impl Iterator<Item = &String> {
+        self.pending_exports.iter()
+    }
+
+    pub fn names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.vars.keys().collect();
+        names.sort();
+        names
+    }
+
+    // negative indices count from the end of the array
+    fn resolve_index(map: &BTreeMap<usize, String>, key: &str) -> Result<usize, String> {
+        let index: i64 = key.trim().parse().map_err(|_| format!("{}: invalid array index", key))?;
+        if index >= 0 {
+            return Ok(index as usize);
+        }
+        let len = map.keys().next_back().map(|k| *k as i64 + 1).unwrap_or(0);
+        if len.saturating_add(index) < 0 {
+            return Err(format!("{}: bad array subscript", key));
+        }
+        Ok((len + index) as usize)
+    }
+}
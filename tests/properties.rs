@@ -0,0 +1,79 @@
+// property tests for the parser, the expander and the formatter, run with `cargo test`
+use fesh::command::word::Word;
+use fesh::expander::Expander;
+use fesh::formatter::Formatter;
+use fesh::input_parser::InputParser;
+use fesh::util;
+use proptest::prelude::*;
+
+// inputs made mostly of characters the tokenizer treats specially
+fn shell_input() -> impl Strategy<Value = String> {
+    prop_oneof![
+        any::<String>(),
+        "[a-z0-9 |<>&'\"\\\\$@*#{}\\[\\]();:=+!~\n\t-]{0,40}",
+    ]
+}
+
+fn expand(expander: &Expander, input: &str) -> Vec<Vec<String>> {
+    let parser = InputParser::new();
+    match parser.parse_raw(input.to_string()) {
+        Ok(command_list) => command_list
+            .commands
+            .iter()
+            .map(|command| expander.expand_words(&command.words))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+proptest! {
+    #[test]
+    fn parsing_never_panics(input in shell_input()) {
+        let parser = InputParser::new();
+        let _ = parser.tokenizer.tokenize(&input);
+        let _ = parser.tokenizer.tokenize_spanned(&input);
+        let _ = parser.parse(input.clone());
+        let _ = parser.parse_script(&input);
+    }
+
+    #[test]
+    fn expansion_never_panics(input in shell_input(), value in shell_input()) {
+        let mut expander = Expander::new();
+        expander.variables.set_scalar("FESH_PROP_V", value);
+        let _ = expand(&expander, &input);
+        let _ = expander.expand_text(&input);
+    }
+
+    #[test]
+    fn quoted_string_is_a_single_word(s in any::<String>()) {
+        let parser = InputParser::new();
+        let command_list = parser.parse_raw(format!("echo {}", util::quote(&s))).unwrap();
+        prop_assert_eq!(command_list.commands.len(), 1);
+
+        let words: &[Word] = &command_list.commands[0].words;
+        prop_assert_eq!(words.len(), 2);
+        prop_assert_eq!(Expander::new().expand_word(&words[1]), vec![s]);
+    }
+
+    #[test]
+    fn formatting_keeps_the_parse_tree(input in shell_input()) {
+        let parser = InputParser::new();
+        let formatter = Formatter::new();
+        if let (Ok(expected), Ok(formatted)) = (parser.parse_script(&input), formatter.format(&input)) {
+            prop_assert_eq!(parser.parse_script(&formatted).unwrap(), expected);
+            prop_assert_eq!(formatter.format(&formatted).unwrap(), formatted);
+        }
+    }
+
+    #[test]
+    fn unquoted_expansion_splits_on_whitespace(value in "[a-z \t\n]{0,30}") {
+        let mut expander = Expander::new();
+        expander.variables.set_scalar("FESH_PROP_V", value.clone());
+        let fields = expand(&expander, "echo $FESH_PROP_V");
+        let expected: Vec<String> = std::iter::once("echo")
+            .chain(value.split_whitespace())
+            .map(String::from)
+            .collect();
+        prop_assert_eq!(fields, vec![expected]);
+    }
+}